@@ -1,32 +1,33 @@
 use std;
 use std::fmt;
 
+use bus::Bus;
 use cpu::Cpu;
 use registers::*;
 
 pub trait Storage {
-    fn load(&self, &mut Cpu) -> u8;
+    fn load<B: Bus>(&self, &mut Cpu<B>) -> u8;
     // I wonder if I should split Storage in Input/Output to avoid this default impl?
-    fn store(&self, &mut Cpu, u8) { panic!("Impossible storage type") }
+    fn store<B: Bus>(&self, &mut Cpu<B>, u8) { panic!("Impossible storage type") }
 }
 
 pub struct ImmediateStorage;
 impl Storage for ImmediateStorage {
-    fn load(&self, cpu: &mut Cpu) -> u8 {
-        cpu.load_byte_and_inc_pc()
+    fn load<B: Bus>(&self, cpu: &mut Cpu<B>) -> u8 {
+        cpu.operand as u8
     }
 }
 
 pub enum Indirect { BC, DE, HL, ZeroPage, ZeroPageRegC, HLD, HLI, Immediate }
 impl Storage for Indirect {
-    fn load(&self, cpu: &mut Cpu) -> u8 {
+    fn load<B: Bus>(&self, cpu: &mut Cpu<B>) -> u8 {
         // TODO: Refactor this. I's duplicated in load and store. Put in CPU maybe?
         let address = match *self {
             Indirect::BC => cpu.registers.bc(),
             Indirect::DE => cpu.registers.de(),
             Indirect::HL => cpu.registers.hl(),
-            Indirect::Immediate => cpu.load_word_and_inc_pc(),
-            Indirect::ZeroPage => 0xFF00 as u16 | cpu.load_byte_and_inc_pc() as u16,
+            Indirect::Immediate => cpu.operand,
+            Indirect::ZeroPage => 0xFF00 as u16 | cpu.operand,
             Indirect::ZeroPageRegC => 0xFF00 as u16 | cpu.registers.c as u16,
             Indirect::HLI => {
                 let address = cpu.registers.hl();
@@ -43,14 +44,14 @@ impl Storage for Indirect {
         cpu.load_byte(address)
     }
 
-    fn store(&self, cpu: &mut Cpu, value: u8) {
+    fn store<B: Bus>(&self, cpu: &mut Cpu<B>, value: u8) {
         // TODO: Refactor this. I's duplicated in load and store. Put in CPU maybe?
         let address = match *self {
             Indirect::BC => cpu.registers.bc(),
             Indirect::DE => cpu.registers.de(),
             Indirect::HL => cpu.registers.hl(),
-            Indirect::Immediate => cpu.load_word_and_inc_pc(),
-            Indirect::ZeroPage => 0xFF00 as u16 | cpu.load_byte_and_inc_pc() as u16,
+            Indirect::Immediate => cpu.operand,
+            Indirect::ZeroPage => 0xFF00 as u16 | cpu.operand,
             Indirect::ZeroPageRegC => 0xFF00 as u16 | cpu.registers.c as u16,
             Indirect::HLI => {
                 let address = cpu.registers.hl();
@@ -69,7 +70,7 @@ impl Storage for Indirect {
 }
 
 impl Storage for Register8 {
-    fn load(&self, cpu: &mut Cpu) -> u8 {
+    fn load<B: Bus>(&self, cpu: &mut Cpu<B>) -> u8 {
         match *self {
             Register8::A => cpu.registers.a,
             Register8::B => cpu.registers.b,
@@ -82,7 +83,7 @@ impl Storage for Register8 {
         }
     }
 
-    fn store(&self, cpu: &mut Cpu, value: u8) {
+    fn store<B: Bus>(&self, cpu: &mut Cpu<B>, value: u8) {
         match *self {
             Register8::A => cpu.registers.a = value,
             Register8::B => cpu.registers.b = value,
@@ -97,7 +98,7 @@ impl Storage for Register8 {
 }
 
 impl Storage for Register16 {
-    fn load(&self, cpu: &mut Cpu) -> u8 {
+    fn load<B: Bus>(&self, cpu: &mut Cpu<B>) -> u8 {
         let address = match *self {
             Register16::AF => cpu.registers.af(),
             Register16::BC => cpu.registers.bc(),
@@ -109,7 +110,7 @@ impl Storage for Register16 {
         cpu.load_byte(address)
     }
 
-    fn store(&self, cpu: &mut Cpu, value: u8) {
+    fn store<B: Bus>(&self, cpu: &mut Cpu<B>, value: u8) {
         let address = match *self {
             Register16::AF => cpu.registers.af(),
             Register16::BC => cpu.registers.bc(),
@@ -122,7 +123,7 @@ impl Storage for Register16 {
     }
 }
 
-impl fmt::Debug for Cpu {
+impl<B: Bus> fmt::Debug for Cpu<B> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         f.write_fmt(format_args!("{:?}", self.registers))
     }
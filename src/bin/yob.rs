@@ -1,20 +1,163 @@
 extern crate yob;
 extern crate sdl2;
+extern crate sdl2_sys;
 
 use sdl2::pixels::PixelFormatEnum;
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
+use sdl2_sys::haptic;
 
-// use yob::cartridge::Cartridge;
-use yob::cpu::Cpu;
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use yob::cartridge::Cartridge;
+use yob::cpu::{Cpu, IllegalOpcodePolicy};
+use yob::joypad::Button;
+use yob::mapper::Kind;
+use yob::memory::Memory;
+use yob::save::SaveFile;
+use yob::trace::Tracer;
+
+// The Game Boy runs at 4194304 Hz and draws a frame every 70224 cycles (~59.7 fps)
+const CLOCK_SPEED: u64 = 4_194_304;
+const CYCLES_PER_FRAME: u64 = 70_224;
+
+// SDL_HAPTIC_INFINITY, the motor runs until stopped
+const RUMBLE_FOREVER: u32 = 0xFFFF_FFFF;
+
+// The first haptic device able to rumble, gamepads included. sdl2 doesn't wrap
+// haptics yet so this goes straight to SDL.
+struct Rumble {
+    device: *mut haptic::SDL_Haptic,
+}
+
+impl Rumble {
+    fn open() -> Option<Rumble> {
+        unsafe {
+            for index in 0..haptic::SDL_NumHaptics() {
+                let device = haptic::SDL_HapticOpen(index);
+                if device.is_null() {
+                    continue;
+                }
+                if haptic::SDL_HapticRumbleInit(device) == 0 {
+                    return Some(Rumble { device: device });
+                }
+                haptic::SDL_HapticClose(device);
+            }
+        }
+        None
+    }
+
+    fn set(&self, on: bool) {
+        unsafe {
+            if on {
+                haptic::SDL_HapticRumblePlay(self.device, 0.75, RUMBLE_FOREVER);
+            } else {
+                haptic::SDL_HapticRumbleStop(self.device);
+            }
+        }
+    }
+}
+
+impl Drop for Rumble {
+    fn drop(&mut self) {
+        unsafe { haptic::SDL_HapticClose(self.device) }
+    }
+}
+
+fn button(keycode: Keycode) -> Option<Button> {
+    match keycode {
+        Keycode::Right => Some(Button::Right),
+        Keycode::Left => Some(Button::Left),
+        Keycode::Up => Some(Button::Up),
+        Keycode::Down => Some(Button::Down),
+        Keycode::Z => Some(Button::A),
+        Keycode::X => Some(Button::B),
+        Keycode::Backspace => Some(Button::Select),
+        Keycode::Return => Some(Button::Start),
+        _ => None,
+    }
+}
 
 fn main() {
-    // let mut file = File::open("roms/tetris.gb").unwrap();
-    // let cartridge = Cartridge::load(&mut file);
-    // println!("{:?}", cartridge.rom);
+    // yob [rom] [--trace file] [--mapper name] [--no-wall-clock]
+    //     [--illegal-opcode lockup|error|break] [--ld-b-b-breakpoint] [--strict]
+    let mut path = "roms/tetris.gb".to_string();
+    let mut trace_path = None;
+    let mut mapper_name = None;
+    let mut wall_clock = true;
+    let mut policy_name = None;
+    let mut ld_b_b_breakpoint = false;
+    let mut strict = false;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--trace" => trace_path = args.next(),
+            "--mapper" => mapper_name = args.next(),
+            "--no-wall-clock" => wall_clock = false,
+            "--illegal-opcode" => policy_name = args.next(),
+            "--ld-b-b-breakpoint" => ld_b_b_breakpoint = true,
+            "--strict" => strict = true,
+            _ => path = arg,
+        }
+    }
+
+    let forced = match mapper_name {
+        Some(name) => match Kind::from_name(&name) {
+            Some(kind) => Some(kind),
+            None => {
+                println!("Unknown mapper {}, try one of {}", name, Kind::names().join(", "));
+                return;
+            }
+        },
+        None => None,
+    };
+
+    let policy = match policy_name {
+        Some(name) => match IllegalOpcodePolicy::from_name(&name) {
+            Some(policy) => policy,
+            None => {
+                println!("Unknown illegal opcode policy {}, try lockup, error or break", name);
+                return;
+            }
+        },
+        None => IllegalOpcodePolicy::Lockup,
+    };
+
+    let mut rom = Vec::new();
+    if let Err(error) = File::open(&path).and_then(|mut file| file.read_to_end(&mut rom)) {
+        println!("Cannot open {}: {}", path, error);
+        return;
+    }
+
+    // Don't hold bogus headers against unlicensed carts or forced mappers
+    let kind = forced.or_else(|| Kind::detect_unlicensed(&rom));
+    let loaded = if kind.is_some() { Cartridge::unchecked(rom) } else { Cartridge::new(rom) };
+    let cartridge = match loaded {
+        Ok(cartridge) => cartridge,
+        Err(error) => {
+            println!("Cannot load {}: {}", path, error);
+            return;
+        }
+    };
+
+    let kind = match kind.or_else(|| Kind::detect(&cartridge)) {
+        Some(kind) => kind,
+        None => {
+            println!("Unsupported cartridge type {:02X}, running it without a mapper",
+                     cartridge.header.cartridge_type);
+            Kind::RomOnly
+        }
+    };
 
     let sdl_context = sdl2::init().unwrap();
     let video_subsystem = sdl_context.video().unwrap();
+    // Rumble carts still work without it
+    let haptic_subsystem = sdl_context.haptic().ok();
+    let rumble = haptic_subsystem.as_ref().and_then(|_| Rumble::open());
 
     let window = video_subsystem.window("yob", 160, 144)
         .position_centered()
@@ -29,30 +172,111 @@ fn main() {
     let mut event_pump = sdl_context.event_pump().unwrap();
     let mut texture = renderer.create_texture_target(PixelFormatEnum::BGR24, 160, 144).unwrap();
 
-    let mut cpu = Cpu::new();
+    let mut save = if cartridge.header.has_battery() {
+        let mut save = SaveFile::for_rom(&path);
+        save.wall_clock = wall_clock;
+        Some(save)
+    } else {
+        None
+    };
+
+    let mut cpu = Cpu::new(Memory::with_mapper(kind, cartridge));
+    if let Some(ref mut save) = save {
+        if let Err(error) = save.load(&mut *cpu.bus.mapper) {
+            println!("Cannot load {}: {}", save.path.display(), error);
+            return;
+        }
+    }
     cpu.reset();
+    cpu.illegal_opcode_policy = policy;
+    cpu.ld_b_b_breakpoint = ld_b_b_breakpoint;
+    cpu.bus.strict = strict;
+    if let Some(trace_path) = trace_path {
+        cpu.tracer = Some(Tracer::create(trace_path).expect("Cannot create trace file"));
+    }
+
+    let frame_duration = Duration::from_nanos(CYCLES_PER_FRAME * 1_000_000_000 / CLOCK_SPEED);
+    let mut frame_cycles = 0;
+    let mut frame_start = Instant::now();
 
     'running: loop {
-        cpu.step();
+        match cpu.step() {
+            Ok(info) => frame_cycles += info.cycles,
+            Err(error) => {
+                println!("{} {:?}", error, cpu);
+                break 'running
+            }
+        }
+
+        // There's no debugger to hand over to yet
+        if cpu.breakpoint {
+            println!("Breakpoint at {:04X} {:?}", cpu.registers.pc, cpu);
+            break 'running
+        }
+
+        // Sleep off whatever is left of the frame to run at the real speed
+        if frame_cycles >= CYCLES_PER_FRAME {
+            frame_cycles -= CYCLES_PER_FRAME;
+            let elapsed = frame_start.elapsed();
+            if elapsed < frame_duration {
+                thread::sleep(frame_duration - elapsed);
+            }
+            frame_start = Instant::now();
+
+            if let Some(on) = cpu.bus.mapper.take_rumble_change() {
+                if let Some(ref rumble) = rumble {
+                    rumble.set(on);
+                }
+            }
+
+            if let Some(ref mut save) = save {
+                if let Err(error) = save.autosave(&mut *cpu.bus.mapper, CYCLES_PER_FRAME) {
+                    println!("Cannot save {}: {}", save.path.display(), error);
+                }
+            }
+        }
 
-        if cpu.memory.gpu.new_frame {
-            texture.update(None, &cpu.memory.gpu.frame_content, 160 * 3).unwrap();
+        if cpu.bus.gpu.new_frame {
+            texture.update(None, &cpu.bus.gpu.frame_content, 160 * 3).unwrap();
             renderer.clear();
             renderer.copy(&texture, None, None);
             renderer.present();
         }
-        cpu.memory.gpu.new_frame = false;
+        cpu.bus.gpu.new_frame = false;
 
         while let Some(event) = event_pump.poll_event() {
             match event {
-                Event::KeyDown { keycode: Some(Keycode::A), .. } => {
-                    cpu.memory.gpu.step(0);
-                }
                 Event::Quit {..} | Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
                     break 'running
                 },
+                Event::KeyDown { keycode: Some(keycode), .. } => {
+                    if let Some(button) = button(keycode) {
+                        cpu.bus.joypad.press(button);
+                    }
+                }
+                Event::KeyUp { keycode: Some(keycode), .. } => {
+                    if let Some(button) = button(keycode) {
+                        cpu.bus.joypad.release(button);
+                    }
+                }
+                Event::MouseMotion { x, y, .. } => {
+                    // Tilt carts lean towards the mouse, 1 g at the edges of the window
+                    let tilt_x = x as f32 / 80.0 - 1.0;
+                    let tilt_y = y as f32 / 72.0 - 1.0;
+                    cpu.bus.mapper.tilt(tilt_x, tilt_y);
+                }
                 _ => ()
             }
         }
     }
+
+    if let Some(ref mut save) = save {
+        if let Err(error) = save.write(&mut *cpu.bus.mapper) {
+            println!("Cannot save {}: {}", save.path.display(), error);
+        }
+    }
+
+    if let Some(ref mut tracer) = cpu.tracer {
+        tracer.flush().expect("Cannot write trace file");
+    }
 }
@@ -0,0 +1,50 @@
+use error::BusFault;
+
+// Everything the CPU talks to. The full Game Boy is a bus (see Memory), but so
+// can be a flat chunk of RAM for tests or something recording every access.
+pub trait Bus {
+    fn load(&mut self, address: u16) -> u8;
+    fn store(&mut self, address: u16, value: u8);
+
+    // Look at memory for a debugger or tracer, without anything noticing
+    fn peek(&mut self, address: u16) -> u8 { self.load(address) }
+
+    // Advance the rest of the hardware by the given amount of clock cycles
+    fn tick(&mut self, cycles: u64);
+
+    // Called on STOP, which resets DIV. Returns true if the CPU switched speed
+    // instead of stopping.
+    fn stop(&mut self) -> bool { false }
+
+    // The first unmapped access since the last call, if any
+    fn take_fault(&mut self) -> Option<BusFault> { None }
+}
+
+// 64 kB of RAM and nothing else
+pub struct FlatBus {
+    pub memory: Vec<u8>,
+    pub cycles: u64,
+}
+
+impl FlatBus {
+    pub fn new() -> FlatBus {
+        FlatBus {
+            memory: vec![0; 0x10000],
+            cycles: 0,
+        }
+    }
+}
+
+impl Bus for FlatBus {
+    fn load(&mut self, address: u16) -> u8 {
+        self.memory[address as usize]
+    }
+
+    fn store(&mut self, address: u16, value: u8) {
+        self.memory[address as usize] = value;
+    }
+
+    fn tick(&mut self, cycles: u64) {
+        self.cycles += cycles;
+    }
+}
@@ -1,25 +1,172 @@
 use std::fs::File;
 use std::io::prelude::*;
 
-// pub struct Headers {
-//     garbage: [u8; 100],
-// }
+use error::CartridgeError;
+
+// What the boot ROM checks for at 0x104 before starting the game
+pub const LOGO: [u8; 0x30] = [
+    0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83,
+    0x00, 0x0C, 0x00, 0x0D, 0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E,
+    0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99, 0xBB, 0xBB, 0x67, 0x63,
+    0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
+];
+
+// http://gbdev.gg8.se/wiki/articles/The_Cartridge_Header
+#[derive(Clone,PartialEq,Debug)]
+pub struct Header {
+    pub title: String,
+    pub manufacturer: String, // Only on newer cartridges, empty otherwise
+    pub cgb_flag: u8, // 0x80 works on both, 0xC0 is CGB only
+    pub new_licensee: String, // Used when old_licensee is 0x33
+    pub sgb_flag: u8, // 0x03 when using SGB functions
+    pub cartridge_type: u8, // Mapper and extra hardware
+    pub rom_size_code: u8,
+    pub ram_size_code: u8,
+    pub destination: u8, // 0x00 for Japan
+    pub old_licensee: u8,
+    pub version: u8,
+    pub header_checksum: u8,
+    pub global_checksum: u16,
+}
+
+impl Header {
+    // Reads and checks the header
+    pub fn parse(rom: &[u8]) -> Result<Header, CartridgeError> {
+        let header = Header::read(rom)?;
+
+        let checksum = Header::checksum(rom);
+        if header.header_checksum != checksum {
+            return Err(CartridgeError::HeaderChecksum { stored: header.header_checksum, computed: checksum });
+        }
+        if header.rom_size().is_none() {
+            return Err(CartridgeError::UnknownRomSize(header.rom_size_code));
+        }
+        if header.ram_size().is_none() {
+            return Err(CartridgeError::UnknownRamSize(header.ram_size_code));
+        }
+
+        Ok(header)
+    }
+
+    // Reads the header as is, whatever is in there
+    pub fn read(rom: &[u8]) -> Result<Header, CartridgeError> {
+        if rom.len() < 0x150 {
+            return Err(CartridgeError::Truncated { expected: 0x150, actual: rom.len() });
+        }
+
+        // CGB cartridges took the end of the title for the flag and a manufacturer code
+        let cgb_flag = rom[0x143];
+        let cgb = cgb_flag & 0x80 != 0;
+        let manufacturer = &rom[0x13F..0x143];
+        let has_manufacturer = cgb && manufacturer.iter().all(|&c| {
+            (c as char).is_ascii_uppercase() || (c as char).is_ascii_digit()
+        });
+        let title_end = if has_manufacturer { 0x13F } else if cgb { 0x143 } else { 0x144 };
+
+        Ok(Header {
+            title: text(&rom[0x134..title_end]),
+            manufacturer: if has_manufacturer { text(manufacturer) } else { String::new() },
+            cgb_flag: cgb_flag,
+            new_licensee: text(&rom[0x144..0x146]),
+            sgb_flag: rom[0x146],
+            cartridge_type: rom[0x147],
+            rom_size_code: rom[0x148],
+            ram_size_code: rom[0x149],
+            destination: rom[0x14A],
+            old_licensee: rom[0x14B],
+            version: rom[0x14C],
+            header_checksum: rom[0x14D],
+            global_checksum: (rom[0x14E] as u16) << 8 | rom[0x14F] as u16,
+        })
+    }
+
+    // What the boot ROM checks before starting the game
+    pub fn checksum(rom: &[u8]) -> u8 {
+        rom[0x134..0x14D].iter().fold(0u8, |x, &byte| x.wrapping_sub(byte).wrapping_sub(1))
+    }
+
+    // In bytes, 32 kB times a power of two
+    pub fn rom_size(&self) -> Option<usize> {
+        match self.rom_size_code {
+            0x00...0x08 => Some(0x8000 << self.rom_size_code),
+            0x52 => Some(72 * 0x4000),
+            0x53 => Some(80 * 0x4000),
+            0x54 => Some(96 * 0x4000),
+            _ => None,
+        }
+    }
+
+    // External RAM in bytes. MBC2 has its own and says 0.
+    pub fn ram_size(&self) -> Option<usize> {
+        match self.ram_size_code {
+            0x00 => Some(0),
+            0x01 => Some(0x800),
+            0x02 => Some(0x2000),
+            0x03 => Some(0x8000),
+            0x04 => Some(0x20000),
+            0x05 => Some(0x10000),
+            _ => None,
+        }
+    }
+
+    // Whether the cartridge RAM (or clock, or EEPROM) keeps going with the power off
+    pub fn has_battery(&self) -> bool {
+        match self.cartridge_type {
+            0x03 | 0x06 | 0x09 | 0x0D | 0x0F | 0x10 | 0x13 | 0x1B | 0x1E | 0x22 | 0xFE | 0xFF => true,
+            _ => false,
+        }
+    }
+
+    // Sum of every byte but the checksum itself. Nothing checks it on hardware.
+    pub fn global_checksum_matches(&self, rom: &[u8]) -> bool {
+        let sum = rom.iter().enumerate()
+            .filter(|&(i, _)| i != 0x14E && i != 0x14F)
+            .fold(0u16, |sum, (_, &byte)| sum.wrapping_add(byte as u16));
+        sum == self.global_checksum
+    }
+}
+
+// Header strings are padded with zeros
+fn text(bytes: &[u8]) -> String {
+    bytes.iter()
+        .take_while(|&&c| c != 0)
+        .map(|&c| if c >= 0x20 && c < 0x7F { c as char } else { '?' })
+        .collect()
+}
+
 pub struct Cartridge {
-    // headers: Headers
+    pub header: Header,
     pub rom: Vec<u8>,
 }
 
 impl Cartridge {
-    pub fn load(mut file: &mut File) -> Cartridge {
-        // let headers = Headers {
-        //     garbage: [0xFF; 100],
-        // };
+    pub fn new(rom: Vec<u8>) -> Result<Cartridge, CartridgeError> {
+        let header = Header::parse(&rom)?;
 
-        let mut rom = Vec::new();
-        file.read_to_end(&mut rom).expect("Cannot read file");
+        let size = header.rom_size().unwrap_or(0);
+        if rom.len() < size {
+            return Err(CartridgeError::Truncated { expected: size, actual: rom.len() });
+        }
 
-        Cartridge {
+        Ok(Cartridge {
+            header: header,
             rom: rom,
-        }
+        })
+    }
+
+    // For carts with a bogus header, like a lot of unlicensed ones
+    pub fn unchecked(rom: Vec<u8>) -> Result<Cartridge, CartridgeError> {
+        let header = Header::read(&rom)?;
+
+        Ok(Cartridge {
+            header: header,
+            rom: rom,
+        })
+    }
+
+    pub fn load(file: &mut File) -> Result<Cartridge, CartridgeError> {
+        let mut rom = Vec::new();
+        file.read_to_end(&mut rom)?;
+        Cartridge::new(rom)
     }
 }
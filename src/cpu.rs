@@ -1,27 +1,80 @@
 use addressing::*;
-use memory::Memory;
+use bus::Bus;
+use error::EmuError;
+use opcodes::{Operand, OPCODES, CB_OPCODES};
 use registers::*;
+use trace::Tracer;
+
+// What to do when running into one of the unused opcodes
+#[derive(Copy,Clone,PartialEq,Debug)]
+pub enum IllegalOpcodePolicy {
+    Lockup, // Hang the CPU like the hardware does, the rest of the machine keeps running
+    Error, // Stop on the opcode and return an error from step
+    Break, // Stop on the opcode and set the breakpoint flag for the debugger
+}
+
+impl IllegalOpcodePolicy {
+    // As given on the command line
+    pub fn from_name(name: &str) -> Option<IllegalOpcodePolicy> {
+        match name {
+            "lockup" => Some(IllegalOpcodePolicy::Lockup),
+            "error" => Some(IllegalOpcodePolicy::Error),
+            "break" => Some(IllegalOpcodePolicy::Break),
+            _ => None,
+        }
+    }
+}
+
+// What happened during a call to Cpu::step
+#[derive(Copy,Clone,PartialEq,Debug)]
+pub struct StepInfo {
+    pub pc: u16, // Where the instruction was fetched, or where the CPU is waiting
+    pub opcode: Option<u8>, // None when no instruction was executed (halted, interrupt dispatch...)
+    pub cycles: u64,
+}
 
-// TODO: Better flag management (haven't implemented N and H)
-// TODO: Interrupts
-// TODO: CB Instructions
 // FIXME: Refactor JP/JR/CALL/RET conditionals. There's lot of duplication.
-pub struct Cpu {
+pub struct Cpu<B: Bus> {
     pub registers: Registers,
-    pub memory: Memory,
+    pub bus: B,
     pub halt: bool,
-    pub interrupt: bool,
-    pub cycles: u64
+    pub halt_bug: bool, // PC fails to increment after the next opcode fetch
+    pub stopped: bool, // Low power mode, only left when a joypad line goes low
+    pub ime: bool, // Interrupt Master Enable
+    pub ei_delay: u8, // EI enables interrupts only after the following instruction
+    pub locked: bool, // Hung after an illegal opcode, only a reset gets out of it
+    pub breakpoint: bool, // Execution stopped for the debugger until the frontend clears it
+    pub ld_b_b_breakpoint: bool, // LD B,B sets breakpoint, like in BGB. Mooneye's test ROMs rely on it.
+    pub illegal_opcode_policy: IllegalOpcodePolicy,
+    pub cycles: u64, // Total clock cycles elapsed since power on
+    pub tracer: Option<Tracer>, // Logs every instruction when set
+    pub operand: u16, // Immediate data of the current instruction, fetched as the opcode table says
+
+    illegal_opcode: Option<u8>,
+    last_opcode: u8,
+    instruction_cycles: u8, // What the current instruction takes according to the opcode table
 }
 
-impl Cpu {
-    pub fn new() -> Cpu {
+impl<B: Bus> Cpu<B> {
+    pub fn new(bus: B) -> Cpu<B> {
         Cpu {
             registers: Registers::new(),
-            memory: Memory::new(),
+            bus: bus,
             halt: false,
-            interrupt: false,
+            halt_bug: false,
+            stopped: false,
+            ime: false,
+            ei_delay: 0,
+            locked: false,
+            breakpoint: false,
+            ld_b_b_breakpoint: false,
+            illegal_opcode_policy: IllegalOpcodePolicy::Lockup,
             cycles: 0,
+            tracer: None,
+            operand: 0,
+            illegal_opcode: None,
+            last_opcode: 0,
+            instruction_cycles: 0,
         }
     }
 
@@ -32,33 +85,107 @@ impl Cpu {
         self.registers.pc = 0x100;
     }
 
-    pub fn step(&mut self) -> u64 {
-        // let pc = self.registers.pc;
-        // let sp = self.registers.sp;
-        // let b = self.load_byte(pc);
-        // print!("{:04x} {:02x}  {:?}", pc, b, self);
-        // println!(" Stack:{:04x}", self.load_word(sp));
+    // Execute one instruction and return what happened, including the number of
+    // clock cycles it took. On error the instruction went through but the machine
+    // is left as is so it can be inspected.
+    pub fn step(&mut self) -> Result<StepInfo, EmuError> {
+        let start = self.cycles;
+        let pc = self.registers.pc;
+        let idle = StepInfo { pc: pc, opcode: None, cycles: 4 };
+
+        // Frozen, nothing runs and no time passes
+        if self.breakpoint {
+            return Ok(StepInfo { pc: pc, opcode: None, cycles: 0 });
+        }
 
-        // Hard coded fake interrupts to boot Tetris
-        match self.registers.pc {
-            0x02B4 => { self.memory.gpu.ly = 0x94 }
-            0x287E => { self.memory.gpu.ly = 0x91 }
-            _ => {}
+        // The LCD and timer are stopped too so the rest of the hardware isn't stepped
+        if self.stopped {
+            // Wait for one of the joypad lines to go low
+            if self.bus.peek(0xFF00) & 0x0F == 0x0F {
+                self.cycles += 4;
+                return Ok(idle);
+            }
+            self.stopped = false;
         }
-        let instruction = self.load_byte_and_inc_pc();
-        self.execute_instruction(instruction);
 
-        if self.interrupt {
+        // HALT ends as soon as an interrupt is pending, even with IME off
+        if self.locked || (self.halt && self.pending_interrupts() == 0) {
+            self.tick();
+            return Ok(idle);
+        }
+        self.halt = false;
+
+        let mut opcode = None;
+        if self.ime && self.pending_interrupts() != 0 {
+            self.instruction_cycles = 20;
             self.interrupt();
+        } else {
+            if let Some(mut tracer) = self.tracer.take() {
+                // Stop tracing if the output went away
+                if tracer.trace(self).is_ok() {
+                    self.tracer = Some(tracer);
+                }
+            }
+
+            let instruction = self.load_byte_and_inc_pc();
+            if self.halt_bug {
+                self.registers.pc = pc;
+                self.halt_bug = false;
+            }
+
+            opcode = Some(instruction);
+            self.last_opcode = instruction;
+            self.instruction_cycles = OPCODES[instruction as usize].cycles;
+            self.execute_instruction(instruction);
+
+            if let Some(opcode) = self.illegal_opcode.take() {
+                match self.illegal_opcode_policy {
+                    IllegalOpcodePolicy::Lockup => { self.locked = true }
+                    IllegalOpcodePolicy::Error => {
+                        // Leave the machine as it was before the opcode, except for the fetch
+                        self.registers.pc = pc;
+                        return Err(EmuError::IllegalOpcode { pc: pc, opcode: opcode });
+                    }
+                    IllegalOpcodePolicy::Break => {
+                        self.registers.pc = pc;
+                        self.breakpoint = true;
+                    }
+                }
+            }
+
+            if self.ei_delay > 0 {
+                self.ei_delay -= 1;
+                if self.ei_delay == 0 {
+                    self.ime = true;
+                }
+            }
         }
 
-        1u64 // FIXME: replace with real cycles count
+        // Memory accesses took their own time, the remaining cycles are spent inside the CPU
+        debug_assert!(self.cycles - start <= self.instruction_cycles as u64,
+                      "{:02X} took more cycles than the opcode table says", self.last_opcode);
+        while self.cycles - start < self.instruction_cycles as u64 {
+            self.tick();
+        }
+        let cycles = self.cycles - start;
+
+        if let Some(fault) = self.bus.take_fault() {
+            // There is no opcode when dispatching an interrupt, report the last one executed
+            let opcode = opcode.unwrap_or(self.last_opcode);
+            return Err(EmuError::from_fault(fault, pc, opcode));
+        }
+
+        Ok(StepInfo { pc: pc, opcode: opcode, cycles: cycles })
     }
 
+    // PC is past the opcode. The operand is read and PC moved past the instruction
+    // as the opcode table says before the handler runs.
     pub fn execute_instruction(&mut self, instr: u8) {
         use registers::Register8::{A,B,C,D,E,H,L};
         use registers::Register16::{AF,BC,DE,HL,SP};
 
+        self.fetch_operand(instr);
+
         match instr {
             0x00 => { }, // NOP
             0x01 => self.ld_word_immediate(BC),
@@ -76,7 +203,7 @@ impl Cpu {
             0x0D => self.dec(C),
             0x0E => self.ld(C, ImmediateStorage),
             0x0F => self.rrca(),
-            0x10 => panic!("Got STOP!"),
+            0x10 => self.stop(),
             0x11 => self.ld_word_immediate(DE),
             0x12 => self.ld(DE, A),
             0x13 => self.inc_16(DE),
@@ -87,7 +214,7 @@ impl Cpu {
             0x18 => self.jr(),
             0x19 => self.add_hl(DE),
             0x1A => self.ld(A, DE),
-            0x1B => self.dec(DE),
+            0x1B => self.dec_16(DE),
             0x1C => self.inc(E),
             0x1D => self.dec(E),
             0x1E => self.ld(E, ImmediateStorage),
@@ -111,7 +238,7 @@ impl Cpu {
             0x30 => self.jr_unless(CARRY_FLAG),
             0x31 => self.ld_word_immediate(SP),
             0x32 => self.ld(Indirect::HLD, A),
-            0x33 => self.inc(SP),
+            0x33 => self.inc_16(SP),
             0x34 => self.inc(HL),
             0x35 => self.dec(HL),
             0x36 => self.ld(HL, ImmediateStorage),
@@ -124,7 +251,7 @@ impl Cpu {
             0x3D => self.dec(A),
             0x3E => self.ld(A, ImmediateStorage),
             0x3F => self.ccf(),
-            0x40 => self.ld(B, B),
+            0x40 => self.ld_b_b(),
             0x41 => self.ld(B, C),
             0x42 => self.ld(B, D),
             0x43 => self.ld(B, E),
@@ -178,7 +305,7 @@ impl Cpu {
             0x73 => self.ld(HL, E),
             0x74 => self.ld(HL, H),
             0x75 => self.ld(HL, L),
-            0x76 => { self.halt = true },
+            0x76 => self.halt(),
             0x77 => self.ld(HL, A),
             0x78 => self.ld(A, B),
             0x79 => self.ld(A, C),
@@ -316,106 +443,352 @@ impl Cpu {
             0xFD => self.illegal(instr),
             0xFE => self.cp(ImmediateStorage),
             0xFF => self.rst(0x38),
-
-            instr => panic!("{}: Instruction not implemented yet", instr)
         }
     }
 
     pub fn execute_cb_instruction(&mut self, instr: u8) {
         use registers::Register8::{A,B,C,D,E,H,L};
-        // use registers::Register16::{AF,BC,DE,HL,SP};
+        use registers::Register16::HL;
 
         match instr {
+            0x00 => self.rlc(B),
+            0x01 => self.rlc(C),
+            0x02 => self.rlc(D),
+            0x03 => self.rlc(E),
+            0x04 => self.rlc(H),
+            0x05 => self.rlc(L),
+            0x06 => self.rlc(HL),
+            0x07 => self.rlc(A),
+            0x08 => self.rrc(B),
+            0x09 => self.rrc(C),
+            0x0A => self.rrc(D),
+            0x0B => self.rrc(E),
+            0x0C => self.rrc(H),
+            0x0D => self.rrc(L),
+            0x0E => self.rrc(HL),
+            0x0F => self.rrc(A),
+            0x10 => self.rl(B),
+            0x11 => self.rl(C),
+            0x12 => self.rl(D),
+            0x13 => self.rl(E),
+            0x14 => self.rl(H),
+            0x15 => self.rl(L),
+            0x16 => self.rl(HL),
+            0x17 => self.rl(A),
+            0x18 => self.rr(B),
+            0x19 => self.rr(C),
+            0x1A => self.rr(D),
+            0x1B => self.rr(E),
+            0x1C => self.rr(H),
+            0x1D => self.rr(L),
+            0x1E => self.rr(HL),
+            0x1F => self.rr(A),
+            0x20 => self.sla(B),
+            0x21 => self.sla(C),
+            0x22 => self.sla(D),
+            0x23 => self.sla(E),
+            0x24 => self.sla(H),
+            0x25 => self.sla(L),
+            0x26 => self.sla(HL),
+            0x27 => self.sla(A),
+            0x28 => self.sra(B),
+            0x29 => self.sra(C),
+            0x2A => self.sra(D),
+            0x2B => self.sra(E),
+            0x2C => self.sra(H),
+            0x2D => self.sra(L),
+            0x2E => self.sra(HL),
+            0x2F => self.sra(A),
             0x30 => self.swap(B),
             0x31 => self.swap(C),
             0x32 => self.swap(D),
             0x33 => self.swap(E),
             0x34 => self.swap(H),
             0x35 => self.swap(L),
-            // 0x36 => self.swap(HL), TODO
+            0x36 => self.swap(HL),
             0x37 => self.swap(A),
+            0x38 => self.srl(B),
+            0x39 => self.srl(C),
+            0x3A => self.srl(D),
+            0x3B => self.srl(E),
+            0x3C => self.srl(H),
+            0x3D => self.srl(L),
+            0x3E => self.srl(HL),
+            0x3F => self.srl(A),
+            0x40 => self.bit(0, B),
+            0x41 => self.bit(0, C),
+            0x42 => self.bit(0, D),
+            0x43 => self.bit(0, E),
+            0x44 => self.bit(0, H),
+            0x45 => self.bit(0, L),
+            0x46 => self.bit(0, HL),
             0x47 => self.bit(0, A),
-            // 0x80 => self.res(0, B),
-            // 0x81 => self.res(0, C),
-            // 0x82 => self.res(0, D),
-            // 0x83 => self.res(0, E),
-            // 0x84 => self.res(0, H),
-            // 0x85 => self.res(0, L),
-            // 0x86 => self.res(0, Indirect::HL),
+            0x48 => self.bit(1, B),
+            0x49 => self.bit(1, C),
+            0x4A => self.bit(1, D),
+            0x4B => self.bit(1, E),
+            0x4C => self.bit(1, H),
+            0x4D => self.bit(1, L),
+            0x4E => self.bit(1, HL),
+            0x4F => self.bit(1, A),
+            0x50 => self.bit(2, B),
+            0x51 => self.bit(2, C),
+            0x52 => self.bit(2, D),
+            0x53 => self.bit(2, E),
+            0x54 => self.bit(2, H),
+            0x55 => self.bit(2, L),
+            0x56 => self.bit(2, HL),
+            0x57 => self.bit(2, A),
+            0x58 => self.bit(3, B),
+            0x59 => self.bit(3, C),
+            0x5A => self.bit(3, D),
+            0x5B => self.bit(3, E),
+            0x5C => self.bit(3, H),
+            0x5D => self.bit(3, L),
+            0x5E => self.bit(3, HL),
+            0x5F => self.bit(3, A),
+            0x60 => self.bit(4, B),
+            0x61 => self.bit(4, C),
+            0x62 => self.bit(4, D),
+            0x63 => self.bit(4, E),
+            0x64 => self.bit(4, H),
+            0x65 => self.bit(4, L),
+            0x66 => self.bit(4, HL),
+            0x67 => self.bit(4, A),
+            0x68 => self.bit(5, B),
+            0x69 => self.bit(5, C),
+            0x6A => self.bit(5, D),
+            0x6B => self.bit(5, E),
+            0x6C => self.bit(5, H),
+            0x6D => self.bit(5, L),
+            0x6E => self.bit(5, HL),
+            0x6F => self.bit(5, A),
+            0x70 => self.bit(6, B),
+            0x71 => self.bit(6, C),
+            0x72 => self.bit(6, D),
+            0x73 => self.bit(6, E),
+            0x74 => self.bit(6, H),
+            0x75 => self.bit(6, L),
+            0x76 => self.bit(6, HL),
+            0x77 => self.bit(6, A),
+            0x78 => self.bit(7, B),
+            0x79 => self.bit(7, C),
+            0x7A => self.bit(7, D),
+            0x7B => self.bit(7, E),
+            0x7C => self.bit(7, H),
+            0x7D => self.bit(7, L),
+            0x7E => self.bit(7, HL),
+            0x7F => self.bit(7, A),
+            0x80 => self.res(0, B),
+            0x81 => self.res(0, C),
+            0x82 => self.res(0, D),
+            0x83 => self.res(0, E),
+            0x84 => self.res(0, H),
+            0x85 => self.res(0, L),
+            0x86 => self.res(0, HL),
             0x87 => self.res(0, A),
-            // 0x88 => self.res(1, B),
-            // 0x89 => self.res(1, C),
-            // 0x8A => self.res(1, D),
-            // 0x8B => self.res(1, E),
-            // 0x8C => self.res(1, H),
-            // 0x8D => self.res(1, L),
-            // 0x8E => self.res(1, Indirect::HL),
-            // 0x8F => self.res(1, A),
+            0x88 => self.res(1, B),
+            0x89 => self.res(1, C),
+            0x8A => self.res(1, D),
+            0x8B => self.res(1, E),
+            0x8C => self.res(1, H),
+            0x8D => self.res(1, L),
+            0x8E => self.res(1, HL),
+            0x8F => self.res(1, A),
+            0x90 => self.res(2, B),
+            0x91 => self.res(2, C),
+            0x92 => self.res(2, D),
+            0x93 => self.res(2, E),
+            0x94 => self.res(2, H),
+            0x95 => self.res(2, L),
+            0x96 => self.res(2, HL),
+            0x97 => self.res(2, A),
+            0x98 => self.res(3, B),
+            0x99 => self.res(3, C),
+            0x9A => self.res(3, D),
+            0x9B => self.res(3, E),
+            0x9C => self.res(3, H),
+            0x9D => self.res(3, L),
+            0x9E => self.res(3, HL),
+            0x9F => self.res(3, A),
+            0xA0 => self.res(4, B),
+            0xA1 => self.res(4, C),
+            0xA2 => self.res(4, D),
+            0xA3 => self.res(4, E),
+            0xA4 => self.res(4, H),
+            0xA5 => self.res(4, L),
+            0xA6 => self.res(4, HL),
+            0xA7 => self.res(4, A),
+            0xA8 => self.res(5, B),
+            0xA9 => self.res(5, C),
+            0xAA => self.res(5, D),
+            0xAB => self.res(5, E),
+            0xAC => self.res(5, H),
+            0xAD => self.res(5, L),
+            0xAE => self.res(5, HL),
+            0xAF => self.res(5, A),
+            0xB0 => self.res(6, B),
+            0xB1 => self.res(6, C),
+            0xB2 => self.res(6, D),
+            0xB3 => self.res(6, E),
+            0xB4 => self.res(6, H),
+            0xB5 => self.res(6, L),
+            0xB6 => self.res(6, HL),
+            0xB7 => self.res(6, A),
+            0xB8 => self.res(7, B),
+            0xB9 => self.res(7, C),
+            0xBA => self.res(7, D),
+            0xBB => self.res(7, E),
+            0xBC => self.res(7, H),
+            0xBD => self.res(7, L),
+            0xBE => self.res(7, HL),
+            0xBF => self.res(7, A),
+            0xC0 => self.set(0, B),
+            0xC1 => self.set(0, C),
+            0xC2 => self.set(0, D),
+            0xC3 => self.set(0, E),
+            0xC4 => self.set(0, H),
+            0xC5 => self.set(0, L),
+            0xC6 => self.set(0, HL),
             0xC7 => self.set(0, A),
-
-            _ => panic!("{:02x}: CB Instruction not implemented yet", instr)
+            0xC8 => self.set(1, B),
+            0xC9 => self.set(1, C),
+            0xCA => self.set(1, D),
+            0xCB => self.set(1, E),
+            0xCC => self.set(1, H),
+            0xCD => self.set(1, L),
+            0xCE => self.set(1, HL),
+            0xCF => self.set(1, A),
+            0xD0 => self.set(2, B),
+            0xD1 => self.set(2, C),
+            0xD2 => self.set(2, D),
+            0xD3 => self.set(2, E),
+            0xD4 => self.set(2, H),
+            0xD5 => self.set(2, L),
+            0xD6 => self.set(2, HL),
+            0xD7 => self.set(2, A),
+            0xD8 => self.set(3, B),
+            0xD9 => self.set(3, C),
+            0xDA => self.set(3, D),
+            0xDB => self.set(3, E),
+            0xDC => self.set(3, H),
+            0xDD => self.set(3, L),
+            0xDE => self.set(3, HL),
+            0xDF => self.set(3, A),
+            0xE0 => self.set(4, B),
+            0xE1 => self.set(4, C),
+            0xE2 => self.set(4, D),
+            0xE3 => self.set(4, E),
+            0xE4 => self.set(4, H),
+            0xE5 => self.set(4, L),
+            0xE6 => self.set(4, HL),
+            0xE7 => self.set(4, A),
+            0xE8 => self.set(5, B),
+            0xE9 => self.set(5, C),
+            0xEA => self.set(5, D),
+            0xEB => self.set(5, E),
+            0xEC => self.set(5, H),
+            0xED => self.set(5, L),
+            0xEE => self.set(5, HL),
+            0xEF => self.set(5, A),
+            0xF0 => self.set(6, B),
+            0xF1 => self.set(6, C),
+            0xF2 => self.set(6, D),
+            0xF3 => self.set(6, E),
+            0xF4 => self.set(6, H),
+            0xF5 => self.set(6, L),
+            0xF6 => self.set(6, HL),
+            0xF7 => self.set(6, A),
+            0xF8 => self.set(7, B),
+            0xF9 => self.set(7, C),
+            0xFA => self.set(7, D),
+            0xFB => self.set(7, E),
+            0xFC => self.set(7, H),
+            0xFD => self.set(7, L),
+            0xFE => self.set(7, HL),
+            0xFF => self.set(7, A),
         }
     }
 
     // Utility functions
 
+    // Immediate data comes right after the opcode. Bytes the table counts in the
+    // length but not in the operand, like the one after STOP, are skipped unread.
+    fn fetch_operand(&mut self, instr: u8) {
+        let opcode = OPCODES[instr as usize];
+        let (operand, size) = match opcode.operand {
+            Operand::None => (0, 0),
+            Operand::Word => (self.load_word_and_inc_pc(), 2),
+            Operand::Byte | Operand::HighPage | Operand::Relative | Operand::Offset => {
+                (self.load_byte_and_inc_pc() as u16, 1)
+            }
+        };
+        self.operand = operand;
+        self.registers.pc = self.registers.pc.wrapping_add(opcode.length as u16 - 1 - size);
+    }
+
     pub fn load_byte_and_inc_pc(&mut self) -> u8 {
         let pc = self.registers.pc;
-        self.registers.pc += 1;
+        self.registers.pc = pc.wrapping_add(1);
         self.load_byte(pc)
     }
 
+    // One machine cycle goes by for the rest of the hardware
+    fn tick(&mut self) {
+        self.cycles += 4;
+        self.bus.tick(4);
+    }
+
+    // Every memory access takes a machine cycle, the hardware moves on before it happens
     pub fn load_byte(&mut self, address: u16) -> u8 {
-        self.cycles += 1;
-        self.memory.load(address)
+        self.tick();
+        self.bus.load(address)
     }
 
     pub fn store_byte(&mut self, address: u16, value: u8) {
-        self.cycles += 1;
-        self.memory.store(address, value);
+        self.tick();
+        self.bus.store(address, value);
     }
 
     pub fn pop_byte(&mut self) -> u8 {
         let sp = self.registers.sp;
         let byte = self.load_byte(sp);
-        self.registers.sp += 1;
+        self.registers.sp = sp.wrapping_add(1);
         byte
     }
 
     pub fn load_word_and_inc_pc(&mut self) -> u16 {
         let pc = self.registers.pc;
-        self.registers.pc += 2;
+        self.registers.pc = pc.wrapping_add(2);
         self.load_word(pc)
     }
 
     pub fn load_word(&mut self, address: u16) -> u16 {
-        self.cycles += 2;
-        let hi = (self.memory.load(address + 1) as u16) << 8;
-        let lo = self.memory.load(address) as u16;
+        let lo = self.load_byte(address) as u16;
+        let hi = (self.load_byte(address.wrapping_add(1)) as u16) << 8;
         hi | lo
     }
 
     pub fn store_word(&mut self, address: u16, value: u16) {
-        self.cycles += 2;
         let lo = value & 0xFF;
         let hi = (value >> 8) & 0xFF;
         self.store_byte(address, lo as u8);
-        self.store_byte(address + 1, hi as u8);
+        self.store_byte(address.wrapping_add(1), hi as u8);
     }
 
+    // The high byte goes first
     pub fn push_word(&mut self, value: u16) {
-        let sp = self.registers.sp.wrapping_sub(2);
-        if value == 0xF0C9 {
-            panic!("{:?}", self);
-        }
-        self.store_word(sp, value);
+        let sp = self.registers.sp.wrapping_sub(1);
+        self.store_byte(sp, (value >> 8) as u8);
+        let sp = sp.wrapping_sub(1);
+        self.store_byte(sp, value as u8);
         self.registers.sp = sp;
     }
 
     pub fn pop_word(&mut self) -> u16 {
         let sp = self.registers.sp;
         let word = self.load_word(sp);
-        self.registers.sp += 2;
+        self.registers.sp = sp.wrapping_add(2);
         word
     }
 
@@ -428,32 +801,39 @@ impl Cpu {
      * Bit 4: Joypad   INT 60h
      */
     fn interrupt(&mut self) {
-        let int_enable = self.memory.interrupt_enable;
-        let int_flags = self.memory.interrupt_flags;
-        let interrupts = int_enable & int_flags;
-
-        if interrupts != 0 {
-            let int_number = interrupts.trailing_zeros();
-            // TODO: Check for nested interrupts
-            // Reset the triggered interrupt flag
-            self.memory.interrupt_flags &= !(1 << int_number);
-            self.interrupt = false;
-            match int_number {
-                0 => self.rst(0x40),
-                _ => { } // TODO: Other interrupts
-            }
-        }
+        // The lowest bit has the highest priority
+        let int_number = self.pending_interrupts().trailing_zeros();
+        // Reset the triggered interrupt flag
+        let flags = self.bus.peek(0xFF0F);
+        self.bus.store(0xFF0F, flags & !(1 << int_number));
+        self.ime = false;
+
+        // Two wait cycles, PC is pushed, then one more to jump to the handler
+        self.tick();
+        self.tick();
+        let pc = self.registers.pc;
+        self.push_word(pc);
+        self.registers.pc = 0x40 + int_number as u16 * 8;
+        self.tick();
+    }
+
+    // The CPU watches the interrupt lines, this isn't a memory access
+    fn pending_interrupts(&mut self) -> u8 {
+        self.bus.peek(0xFFFF) & self.bus.peek(0xFF0F) & 0x1F
     }
 
     // Instructions implementations
 
+    // The CB opcode was fetched as the prefix's operand
     fn cb(&mut self) {
-        let instruction = self.load_byte_and_inc_pc();
+        let instruction = self.operand as u8;
+        self.instruction_cycles = CB_OPCODES[instruction as usize].cycles;
         self.execute_cb_instruction(instruction);
     }
 
-    fn illegal(&self, instruction: u8) {
-        panic!("Illegal opcode {}", instruction);
+    // The policy is applied by step, which knows where the opcode came from
+    fn illegal(&mut self, instruction: u8) {
+        self.illegal_opcode = Some(instruction);
     }
 
     fn ld<In: Storage, Out: Storage>(&mut self, a: Out, b: In) {
@@ -461,6 +841,13 @@ impl Cpu {
         a.store(self, value);
     }
 
+    // A no-op, unless used as a software breakpoint
+    fn ld_b_b(&mut self) {
+        if self.ld_b_b_breakpoint {
+            self.breakpoint = true;
+        }
+    }
+
     fn ld_sp_hl(&mut self) {
         let value = self.registers.hl();
         self.registers.sp = value;
@@ -468,22 +855,31 @@ impl Cpu {
 
     // LD HL,SP+e
     fn ld_hl_sp(&mut self) {
-        // TODO: Handle flags
-        // FIXME: Signed arithmetics?
-        let offset = self.load_byte_and_inc_pc() as u16;
-        let sp = self.registers.sp;
-        let address = offset.wrapping_add(sp);
+        let address = self.sp_plus_offset();
         self.registers.store_16(Register16::HL, address);
     }
 
+    // SP plus a signed immediate, shared by ADD SP,e and LD HL,SP+e.
+    // H and C come from the unsigned addition of the low byte.
+    fn sp_plus_offset(&mut self) -> u16 {
+        let sp = self.registers.sp;
+        let offset = self.operand;
+        self.registers.f = 0;
+        self.registers.set_half_carry((sp & 0xF) + (offset & 0xF) > 0xF);
+        self.registers.set_carry((sp & 0xFF) + offset > 0xFF);
+        // Crazy trick to do signed addition.
+        // u8 -> i8 -> i16 -> u16
+        sp.wrapping_add(offset as u8 as i8 as i16 as u16)
+    }
+
     fn ld_word_immediate(&mut self, register: Register16) {
-        let value = self.load_word_and_inc_pc();
+        let value = self.operand;
         self.registers.store_16(register, value);
     }
 
     fn ld_indirect_sp(&mut self) {
         let value = self.registers.sp;
-        let address = self.load_word_and_inc_pc();
+        let address = self.operand;
         self.store_word(address, value);
     }
 
@@ -494,13 +890,14 @@ impl Cpu {
 
     fn push(&mut self, register: Register16) {
         let value = self.registers.load_16(register);
+        self.tick(); // SP is decremented before the writes
         self.push_word(value);
     }
 
     fn and<S: Storage>(&mut self, s: S) {
         let value = self.registers.a & s.load(self);
         self.registers.a = value;
-        self.registers.f = 0;
+        self.registers.f = HALF_CARRY_FLAG;
         self.registers.set_zero(value == 0);
     }
 
@@ -520,6 +917,7 @@ impl Cpu {
 
     fn rlca(&mut self) {
         let value = self.registers.a.rotate_left(1);
+        self.registers.f = 0;
         self.registers.set_carry(value & 1 != 0);
         self.registers.a = value;
         // let a = self.registers.a;
@@ -529,6 +927,7 @@ impl Cpu {
 
     fn rrca(&mut self) {
         let value = self.registers.a.rotate_right(1);
+        self.registers.f = 0;
         self.registers.set_carry(value & 0x80 != 0);
         self.registers.a = value;
         // let a = self.registers.a;
@@ -539,6 +938,7 @@ impl Cpu {
     fn rla(&mut self) {
         let a = self.registers.a;
         let value = (a << 1) | self.registers.carry();
+        self.registers.f = 0;
         self.registers.set_carry(a & 0x80 != 0);
         self.registers.a = value;
     }
@@ -546,17 +946,21 @@ impl Cpu {
     fn rra(&mut self) {
         let a = self.registers.a;
         let value = (a >> 1) | (self.registers.carry() << 7);
+        self.registers.f = 0;
         self.registers.set_carry(a & 1 != 0);
         self.registers.a = value;
     }
 
-    // Generic addition for ADD and SBC
+    // Generic addition for ADD and ADC
     fn add_op(&mut self, value: u8, carry: bool) {
-        let mut result = (value as u16).wrapping_add(self.registers.a as u16);
-        if carry { result = result.wrapping_add(self.registers.carry() as u16) }
-        self.registers.set_carry(result & 0x100 != 0);
-        self.registers.set_zero(result == 0);
-        self.registers.a = (result as u8) & 0xFF;
+        let a = self.registers.a;
+        let c = if carry { self.registers.carry() } else { 0 };
+        let result = a as u16 + value as u16 + c as u16;
+        self.registers.f = 0;
+        self.registers.set_zero(result & 0xFF == 0);
+        self.registers.set_half_carry((a & 0xF) + (value & 0xF) + c > 0xF);
+        self.registers.set_carry(result > 0xFF);
+        self.registers.a = result as u8;
     }
 
     fn add<S: Storage>(&mut self, s: S) {
@@ -574,27 +978,29 @@ impl Cpu {
         let hl = self.registers.hl();
         let value = self.registers.load_16(register);
         let result = value.wrapping_add(hl);
+        // Z is left untouched
+        self.registers.set_subtract(false);
+        self.registers.set_half_carry((hl & 0xFFF) + (value & 0xFFF) > 0xFFF);
+        self.registers.set_carry(result < hl);
         self.registers.store_16(Register16::HL, result);
     }
 
     // ADD SP, nn
     fn add_sp(&mut self) {
-        // TODO: handle flags
-        let sp = self.registers.sp;
-        // Crazy trick to do signed addition.
-        // u8 -> i8 -> i16 -> u16
-        let value = self.load_byte_and_inc_pc() as i8 as i16 as u16;
-        let result = value.wrapping_add(sp);
+        let result = self.sp_plus_offset();
         self.registers.store_16(Register16::SP, result);
     }
 
     // Generic subtraction for SUB, SBC and CP
     fn sub_op(&mut self, value: u8, carry: bool) -> u8 {
-        let mut result = (self.registers.a as u16).wrapping_sub(value as u16);
-        if carry { result = result.wrapping_sub(self.registers.carry() as u16) }
-        self.registers.set_carry(result & 0x100 != 0);
+        let a = self.registers.a;
+        let c = if carry { self.registers.carry() } else { 0 };
+        let result = a.wrapping_sub(value).wrapping_sub(c);
+        self.registers.f = SUB_FLAG;
         self.registers.set_zero(result == 0);
-        (result as u8) & 0xFF
+        self.registers.set_half_carry((a & 0xF) < (value & 0xF) + c);
+        self.registers.set_carry((a as u16) < value as u16 + c as u16);
+        result
     }
 
     fn sub<S: Storage>(&mut self, s: S) {
@@ -612,174 +1018,269 @@ impl Cpu {
         self.sub_op(value, false);
     }
 
+    // INC and DEC leave the carry flag untouched
     fn inc<S: Storage>(&mut self, storage: S) {
         let value = storage.load(self).wrapping_add(1);
-        let carry = self.registers.test_flag(CARRY_FLAG);
         self.registers.set_zero(value == 0);
-        self.registers.set_carry(carry);
+        self.registers.set_subtract(false);
+        self.registers.set_half_carry(value & 0xF == 0);
         storage.store(self, value);
     }
 
+    // 16 bits INC and DEC don't affect any flag
     fn inc_16(&mut self, register: Register16) {
         let value = self.registers.load_16(register.clone()).wrapping_add(1);
-        let carry = self.registers.test_flag(CARRY_FLAG);
-        self.registers.set_zero(value == 0);
-        self.registers.set_carry(carry);
         self.registers.store_16(register, value);
     }
 
     fn dec<S: Storage>(&mut self, storage: S) {
         let value = storage.load(self).wrapping_sub(1);
-        let carry = self.registers.test_flag(CARRY_FLAG);
         self.registers.set_zero(value == 0);
-        self.registers.set_carry(carry);
+        self.registers.set_subtract(true);
+        self.registers.set_half_carry(value & 0xF == 0xF);
         storage.store(self, value);
     }
 
     fn dec_16(&mut self, register: Register16) {
         let value = self.registers.load_16(register.clone()).wrapping_sub(1);
-        let carry = self.registers.test_flag(CARRY_FLAG);
-        self.registers.set_zero(value == 0);
-        self.registers.set_carry(carry);
         self.registers.store_16(register, value);
     }
 
+    // Conditional branches take longer when taken
+    fn branch_taken(&mut self) {
+        self.instruction_cycles = OPCODES[self.last_opcode as usize].branch_cycles;
+    }
+
     fn jp(&mut self) {
-        let address = self.load_word_and_inc_pc();
+        let address = self.operand;
         self.registers.pc = address;
-        self.cycles += 1;
     }
 
     fn jp_hl(&mut self) {
         let address = self.registers.hl();
         self.registers.pc = address;
-        self.cycles += 1;
     }
 
     fn jp_if(&mut self, flag: u8) {
-        let address = self.load_word_and_inc_pc();
+        let address = self.operand;
         if self.registers.test_flag(flag) {
             self.registers.pc = address;
-            self.cycles += 1;
+            self.branch_taken();
         }
     }
 
     fn jp_unless(&mut self, flag: u8) {
-        let address = self.load_word_and_inc_pc();
+        let address = self.operand;
         if !self.registers.test_flag(flag) {
             self.registers.pc = address;
-            self.cycles += 1;
+            self.branch_taken();
         }
     }
 
     fn jr(&mut self) {
-        let offset = self.load_byte_and_inc_pc() as i8;
-        self.registers.pc = (self.registers.pc as i16 + offset as i16) as u16;
-        self.cycles += 1;
+        let offset = self.operand as u8 as i8;
+        self.registers.pc = self.registers.pc.wrapping_add(offset as i16 as u16);
     }
 
     fn jr_if(&mut self, flag: u8) {
-        let offset = self.load_byte_and_inc_pc() as i8;
+        let offset = self.operand as u8 as i8;
         if self.registers.test_flag(flag) {
-            self.registers.pc = (self.registers.pc as i16 + offset as i16) as u16;
-            self.cycles += 1;
+            self.registers.pc = self.registers.pc.wrapping_add(offset as i16 as u16);
+            self.branch_taken();
         }
     }
 
     fn jr_unless(&mut self, flag: u8) {
-        let offset = self.load_byte_and_inc_pc() as i8;
+        let offset = self.operand as u8 as i8;
         if !self.registers.test_flag(flag) {
-            self.registers.pc = (self.registers.pc as i16 + offset as i16) as u16;
-            self.cycles += 1;
+            self.registers.pc = self.registers.pc.wrapping_add(offset as i16 as u16);
+            self.branch_taken();
         }
     }
 
     fn call_op(&mut self, address: u16) {
         //let return_address = self.load_word_and_inc_pc();
         let return_address = self.registers.pc;
+        self.tick();
         self.push_word(return_address);
         self.registers.pc = address;
-        self.cycles += 1;
     }
 
     fn call(&mut self) {
-        let address = self.load_word_and_inc_pc();
+        let address = self.operand;
         self.call_op(address);
     }
 
     fn call_if(&mut self, flag: u8) {
-        let address = self.load_word_and_inc_pc();
+        let address = self.operand;
         if self.registers.test_flag(flag) {
             self.call_op(address);
+            self.branch_taken();
         }
     }
 
     fn call_unless(&mut self, flag: u8) {
-        let address = self.load_word_and_inc_pc();
+        let address = self.operand;
         if !self.registers.test_flag(flag) {
             self.call_op(address);
+            self.branch_taken();
         }
     }
 
     fn ret(&mut self) {
         self.registers.pc = self.pop_word();
-        self.cycles += 1;
     }
 
+    // Checking the condition takes a cycle of its own
     fn ret_if(&mut self, flag: u8) {
+        self.tick();
         if self.registers.test_flag(flag) {
             self.ret();
+            self.branch_taken();
         }
     }
 
     fn ret_unless(&mut self, flag: u8) {
+        self.tick();
         if !self.registers.test_flag(flag) {
             self.ret();
+            self.branch_taken();
         }
     }
 
     fn reti(&mut self) {
-        self.interrupt = false;
+        // Unlike EI, RETI enables interrupts right away
+        self.ime = true;
         self.ret();
     }
 
     fn ei(&mut self) {
-        self.interrupt = true;
+        self.ei_delay = 2;
     }
 
     fn di(&mut self) {
-        self.interrupt = false;
+        self.ime = false;
+        self.ei_delay = 0;
+    }
+
+    fn stop(&mut self) {
+        // On CGB, STOP is also how the CPU switches speed
+        if !self.bus.stop() {
+            self.stopped = true;
+        }
+    }
+
+    fn halt(&mut self) {
+        if !self.ime && self.pending_interrupts() != 0 {
+            // DMG bug: with IME off and an interrupt already pending, HALT exits
+            // immediately and the next byte gets read twice.
+            self.halt_bug = true;
+        } else {
+            self.halt = true;
+        }
     }
 
     fn ccf(&mut self) {
-        // TODO: Reset N and H
         let carry = self.registers.test_flag(CARRY_FLAG);
+        self.registers.set_subtract(false);
+        self.registers.set_half_carry(false);
         self.registers.set_carry(!carry);
     }
 
     fn scf(&mut self) {
-        // TODO: Reset N and H
+        self.registers.set_subtract(false);
+        self.registers.set_half_carry(false);
         self.registers.set_carry(true);
     }
 
     fn cpl(&mut self) {
         self.registers.a = !self.registers.a;
+        self.registers.set_subtract(true);
+        self.registers.set_half_carry(true);
     }
 
     fn rst(&mut self, address: u16) {
         let pc = self.registers.pc;
+        self.tick();
         self.push_word(pc);
         self.registers.pc = address;
-        self.cycles += 1;
     }
 
+    // Adjust A back to BCD after an addition or subtraction of two BCD numbers.
+    // N tells which operation happened, H and C which digits overflowed.
     fn daa(&mut self) {
-        println!("{:?}", self);
-        panic!("DAA not implemented yet");
+        let a = self.registers.a;
+        let subtract = self.registers.test_flag(SUB_FLAG);
+        let mut correction = 0;
+        let mut carry = false;
+
+        if self.registers.test_flag(HALF_CARRY_FLAG) || (!subtract && a & 0xF > 0x9) {
+            correction |= 0x06;
+        }
+
+        if self.registers.test_flag(CARRY_FLAG) || (!subtract && a > 0x99) {
+            correction |= 0x60;
+            carry = true;
+        }
+
+        let value = if subtract { a.wrapping_sub(correction) } else { a.wrapping_add(correction) };
+        self.registers.a = value;
+        self.registers.set_zero(value == 0);
+        self.registers.set_half_carry(false);
+        self.registers.set_carry(carry);
     }
 
     // CB Instructions
 
+    // Shared flag handling for the CB rotates and shifts: Z and C, N and H reset
+    fn shift_op<S: Storage>(&mut self, storage: S, value: u8, carry: bool) {
+        self.registers.f = 0;
+        self.registers.set_zero(value == 0);
+        self.registers.set_carry(carry);
+        storage.store(self, value);
+    }
+
+    fn rlc<S: Storage>(&mut self, storage: S) {
+        let original = storage.load(self);
+        let value = original.rotate_left(1);
+        self.shift_op(storage, value, original & 0x80 != 0);
+    }
+
+    fn rrc<S: Storage>(&mut self, storage: S) {
+        let original = storage.load(self);
+        let value = original.rotate_right(1);
+        self.shift_op(storage, value, original & 1 != 0);
+    }
+
+    fn rl<S: Storage>(&mut self, storage: S) {
+        let original = storage.load(self);
+        let value = (original << 1) | self.registers.carry();
+        self.shift_op(storage, value, original & 0x80 != 0);
+    }
+
+    fn rr<S: Storage>(&mut self, storage: S) {
+        let original = storage.load(self);
+        let value = (original >> 1) | (self.registers.carry() << 7);
+        self.shift_op(storage, value, original & 1 != 0);
+    }
+
+    fn sla<S: Storage>(&mut self, storage: S) {
+        let original = storage.load(self);
+        self.shift_op(storage, original << 1, original & 0x80 != 0);
+    }
+
+    // Arithmetic shift: bit 7 is kept
+    fn sra<S: Storage>(&mut self, storage: S) {
+        let original = storage.load(self);
+        let value = (original >> 1) | (original & 0x80);
+        self.shift_op(storage, value, original & 1 != 0);
+    }
+
+    fn srl<S: Storage>(&mut self, storage: S) {
+        let original = storage.load(self);
+        self.shift_op(storage, original >> 1, original & 1 != 0);
+    }
+
     fn swap<S: Storage>(&mut self, register: S) {
         let original = register.load(self);
         let value = (original >> 4) | (original << 4);
@@ -790,6 +1291,8 @@ impl Cpu {
 
     fn bit<S: Storage>(&mut self, bit: u8, register: S) {
         let value = register.load(self) & (1 << bit);
+        // N is reset, H is set and C is left untouched
+        self.registers.f = (self.registers.f & CARRY_FLAG) | HALF_CARRY_FLAG;
         self.registers.set_zero(value == 0);
     }
 
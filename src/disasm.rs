@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use bus::Bus;
+use opcodes::{Operand, OPCODES, CB_OPCODES};
+
+#[derive(Clone,PartialEq,Debug)]
+pub struct Instruction {
+    pub text: String, // e.g. "JR NZ,$0150"
+    pub length: u8,
+}
+
+// Labels to show instead of addresses, keyed by address
+pub struct Symbols {
+    labels: HashMap<u16, String>,
+}
+
+impl Symbols {
+    pub fn new() -> Symbols {
+        Symbols { labels: HashMap::new() }
+    }
+
+    // Reads a .sym file as written by RGBDS or wla-dx: "bank:address label" lines
+    // and comments starting with a semicolon. Banks are ignored.
+    pub fn parse(source: &str) -> Symbols {
+        let mut symbols = Symbols::new();
+
+        for line in source.lines() {
+            let line = line.split(';').next().unwrap_or("").trim();
+            let mut parts = line.split_whitespace();
+            let (location, label) = match (parts.next(), parts.next()) {
+                (Some(location), Some(label)) => (location, label),
+                _ => continue,
+            };
+
+            let address = location.rsplit(':').next().unwrap_or(location);
+            if let Ok(address) = u16::from_str_radix(address, 16) {
+                symbols.insert(address, label);
+            }
+        }
+
+        symbols
+    }
+
+    pub fn insert(&mut self, address: u16, label: &str) {
+        self.labels.insert(address, label.to_string());
+    }
+
+    pub fn get(&self, address: u16) -> Option<&str> {
+        self.labels.get(&address).map(|label| label.as_str())
+    }
+}
+
+// Decodes the instruction at the start of bytes, which were read from address.
+// Missing bytes past the end of the slice read as zero.
+pub fn disassemble(bytes: &[u8], address: u16, symbols: Option<&Symbols>) -> Instruction {
+    let byte = |i: usize| bytes.get(i).cloned().unwrap_or(0);
+
+    let opcode = OPCODES[byte(0) as usize];
+    if opcode.is_illegal() {
+        return Instruction { text: format!("DB ${:02X}", byte(0)), length: 1 };
+    }
+
+    if byte(0) == 0xCB {
+        let opcode = CB_OPCODES[byte(1) as usize];
+        return Instruction { text: opcode.mnemonic.to_string(), length: opcode.length };
+    }
+
+    let word = (byte(2) as u16) << 8 | byte(1) as u16;
+    let label = |address: u16| {
+        match symbols.and_then(|symbols| symbols.get(address)) {
+            Some(label) => label.to_string(),
+            None => format!("${:04X}", address),
+        }
+    };
+
+    let mnemonic = opcode.mnemonic;
+    let text = match opcode.operand {
+        Operand::None => mnemonic.to_string(),
+        Operand::Byte => mnemonic.replace("d8", &format!("${:02X}", byte(1))),
+        Operand::Word if mnemonic.contains("a16") => mnemonic.replace("a16", &label(word)),
+        Operand::Word => mnemonic.replace("d16", &format!("${:04X}", word)),
+        Operand::HighPage => mnemonic.replace("a8", &label(0xFF00 | byte(1) as u16)),
+        Operand::Relative => {
+            // Relative to the instruction that follows
+            let offset = byte(1) as i8;
+            let target = address.wrapping_add(opcode.length as u16).wrapping_add(offset as u16);
+            mnemonic.replace("r8", &label(target))
+        }
+        Operand::Offset => {
+            // SP+r8 becomes SP-$02 for negative offsets
+            let offset = byte(1) as i8;
+            if offset < 0 {
+                mnemonic.replace("+r8", "r8").replace("r8", &format!("-${:02X}", -(offset as i16)))
+            } else {
+                mnemonic.replace("r8", &format!("${:02X}", offset))
+            }
+        }
+    };
+
+    Instruction { text: text, length: opcode.length }
+}
+
+// Same as disassemble, peeking at the bytes on a bus
+pub fn disassemble_at<B: Bus>(bus: &mut B, address: u16, symbols: Option<&Symbols>) -> Instruction {
+    let bytes = [
+        bus.peek(address),
+        bus.peek(address.wrapping_add(1)),
+        bus.peek(address.wrapping_add(2)),
+    ];
+    disassemble(&bytes, address, symbols)
+}
@@ -0,0 +1,102 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+// An access to an address nothing answers to, latched by the bus until the CPU picks it up
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum BusFault {
+    Read(u16),
+    Write(u16, u8),
+}
+
+// The PC and opcode are the ones of the instruction that caused the error
+#[derive(Debug,Clone,PartialEq)]
+pub enum EmuError {
+    IllegalOpcode { pc: u16, opcode: u8 },
+    UnmappedRead { pc: u16, opcode: u8, address: u16 },
+    UnmappedWrite { pc: u16, opcode: u8, address: u16, value: u8 },
+}
+
+impl EmuError {
+    pub fn from_fault(fault: BusFault, pc: u16, opcode: u8) -> EmuError {
+        match fault {
+            BusFault::Read(address) => {
+                EmuError::UnmappedRead { pc: pc, opcode: opcode, address: address }
+            }
+            BusFault::Write(address, value) => {
+                EmuError::UnmappedWrite { pc: pc, opcode: opcode, address: address, value: value }
+            }
+        }
+    }
+}
+
+impl fmt::Display for EmuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            EmuError::IllegalOpcode { pc, opcode } => {
+                write!(f, "Illegal opcode {:02X} at {:04X}", opcode, pc)
+            }
+            EmuError::UnmappedRead { pc, opcode, address } => {
+                write!(f, "Can't read memory at {:04X} (opcode {:02X} at {:04X})", address, opcode, pc)
+            }
+            EmuError::UnmappedWrite { pc, opcode, address, value } => {
+                write!(f, "Can't write {:02X} to memory at {:04X} (opcode {:02X} at {:04X})",
+                       value, address, opcode, pc)
+            }
+        }
+    }
+}
+
+impl Error for EmuError {
+    fn description(&self) -> &str {
+        match *self {
+            EmuError::IllegalOpcode { .. } => "illegal opcode",
+            EmuError::UnmappedRead { .. } => "read from unmapped memory",
+            EmuError::UnmappedWrite { .. } => "write to unmapped memory",
+        }
+    }
+}
+
+// Why a ROM image can't be used
+#[derive(Debug)]
+pub enum CartridgeError {
+    Io(io::Error),
+    Truncated { expected: usize, actual: usize },
+    HeaderChecksum { stored: u8, computed: u8 },
+    UnknownRomSize(u8),
+    UnknownRamSize(u8),
+}
+
+impl From<io::Error> for CartridgeError {
+    fn from(error: io::Error) -> CartridgeError {
+        CartridgeError::Io(error)
+    }
+}
+
+impl fmt::Display for CartridgeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CartridgeError::Io(ref error) => write!(f, "Can't read the ROM: {}", error),
+            CartridgeError::Truncated { expected, actual } => {
+                write!(f, "ROM is truncated: {} bytes, expected at least {}", actual, expected)
+            }
+            CartridgeError::HeaderChecksum { stored, computed } => {
+                write!(f, "Header checksum is {:02X} but the header sums to {:02X}", stored, computed)
+            }
+            CartridgeError::UnknownRomSize(code) => write!(f, "Unknown ROM size {:02X}", code),
+            CartridgeError::UnknownRamSize(code) => write!(f, "Unknown RAM size {:02X}", code),
+        }
+    }
+}
+
+impl Error for CartridgeError {
+    fn description(&self) -> &str {
+        match *self {
+            CartridgeError::Io(_) => "can't read the ROM",
+            CartridgeError::Truncated { .. } => "truncated ROM",
+            CartridgeError::HeaderChecksum { .. } => "bad header checksum",
+            CartridgeError::UnknownRomSize(_) => "unknown ROM size",
+            CartridgeError::UnknownRamSize(_) => "unknown RAM size",
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use memory::{VBLANK_INTERRUPT, STAT_INTERRUPT};
+
 pub struct Gpu {
     pub lcd_control: u8,
     pub lcd_status: u8,
@@ -13,9 +15,9 @@ pub struct Gpu {
 
     pub oam: [u8; 0xA0],
     pub vram: [u8; 0x2000],
-    // TODO: DMACONT
 
     pub new_frame: bool,
+    pub interrupts: u8, // Interrupts requested since the last step
     pub frame_content: [u8; 160 * 144 * 3],
 
     pub mode: VideoMode,
@@ -47,6 +49,7 @@ impl Gpu {
             oam: [0; 0xA0],
             vram: [0; 0x2000],
             new_frame: false,
+            interrupts: 0,
             frame_content: [0xFF; 160 * 144 * 3],
             mode: VideoMode::HBlank,
             cycles: 0,
@@ -54,29 +57,43 @@ impl Gpu {
     }
 
     pub fn step(&mut self, cycles: u64) {
-        self.cycles = cycles;
+        // Nothing moves while the LCD is off
+        if self.lcd_control & 0x80 == 0 {
+            return;
+        }
+
+        self.cycles += cycles;
 
         if self.cycles > 455 {
             self.cycles -= 456;
             self.ly = (self.ly + 1) % 154;
 
-            if self.ly >= 144 && self.mode != VideoMode::VBLank {
+            if self.ly == 144 {
                 // switch to VBlank
+                self.draw_frame();
+                self.interrupts |= VBLANK_INTERRUPT;
             }
 
-            if self.ly == self.lyc {
-                // set LCDSTAT interrupt flag
-            }
+            self.compare_ly();
         }
 
-        if self.ly < 144 {
+        // Each visible line reads OAM, then VRAM while drawing, then waits in HBlank
+        let mode = if self.ly >= 144 {
+            VideoMode::VBLank
+        } else {
             match self.cycles {
-                0...80   => {} // switch to ReadOam
-                80...252 => {} // switch to ReadRam
-                _        => {} // switch to HBLank
+                0...79   => VideoMode::ReadOam,
+                80...251 => VideoMode::ReadRam,
+                _        => VideoMode::HBlank,
             }
+        };
+
+        if mode != self.mode {
+            self.switch_mode(mode);
         }
+    }
 
+    fn draw_frame(&mut self) {
         for x in 0..16 {
             for y in 0..16 {
                 self.draw_tile(x, y);
@@ -86,15 +103,32 @@ impl Gpu {
         self.new_frame = true;
     }
 
+    // Update the coincidence flag and fire the LCDSTAT interrupt if it's enabled
+    fn compare_ly(&mut self) {
+        if self.ly == self.lyc {
+            self.lcd_status |= 0x04;
+            if self.lcd_status & 0x40 != 0 {
+                self.interrupts |= STAT_INTERRUPT;
+            }
+        } else {
+            self.lcd_status &= !0x04;
+        }
+    }
+
+    // The mode shows in the low bits of STAT. Entering HBlank, VBlank or OAM
+    // search fires the LCDSTAT interrupt if STAT bit 3, 4 or 5 enables it.
     pub fn switch_mode(&mut self, mode: VideoMode) {
         self.mode = mode;
-
-        // We should set the correct interrupt flags here
-        match self.mode {
-            VideoMode::HBlank => { self.make_line(); }
-            VideoMode::VBLank => {}
-            VideoMode::ReadOam => {}
-            VideoMode::ReadRam => {} // Nothing to do here
+        self.lcd_status = (self.lcd_status & !0x03) | mode as u8;
+
+        let source = match self.mode {
+            VideoMode::HBlank => { self.make_line(); 0x08 }
+            VideoMode::VBLank => 0x10,
+            VideoMode::ReadOam => 0x20,
+            VideoMode::ReadRam => 0x00, // No interrupt for this one
+        };
+        if self.lcd_status & source != 0 {
+            self.interrupts |= STAT_INTERRUPT;
         }
     }
 
@@ -143,31 +177,39 @@ impl Gpu {
             0x42 => self.scroll_x,
             0x43 => self.scroll_y,
             0x44 => self.ly,
-            // 0x45 => {} // CMPLINE - Scanline comparison
+            0x45 => self.lyc, // CMPLINE - Scanline comparison
             0x47 => self.bg_palette,
-            // 0x46 => {} // DMACONT - DMA Transfer Controller
-            // 0x48 => {} // OBJ0PAL - Sprite palette #0
-            // 0x49 => {} // OBJ1PAL - Sprite palette #1
-            // 0x4B => {} // WNDPOSX - Window X position
-            _ => panic!("Can't load from GPU yet (0x{:02X})", address)
+            0x48 => self.sprite_palette_0, // OBJ0PAL - Sprite palette #0
+            0x49 => self.sprite_palette_1, // OBJ1PAL - Sprite palette #1
+            0x4A => self.window_x,
+            0x4B => self.window_y,
+            _ => 0xFF
         }
     }
 
     pub fn store(&mut self, address: u8, value: u8) {
         match address {
-            0x40 => { self.lcd_control = value }, // FIXME some bits are read only
-            0x41 => { self.lcd_status = value }, // FIXME some bits are read only
+            0x40 => {
+                // Turning the LCD off resets LY, it restarts from line 0 when turned back on
+                if value & 0x80 == 0 {
+                    self.ly = 0;
+                    self.cycles = 0;
+                    self.mode = VideoMode::HBlank;
+                    self.lcd_status &= !0x03;
+                }
+                self.lcd_control = value;
+            }
+            0x41 => { self.lcd_status = (value & 0x78) | (self.lcd_status & 0x07) }, // Mode and coincidence are read only
             0x42 => { self.scroll_x = value },
             0x43 => { self.scroll_y = value },
             0x44 => { self.ly = 0 },
-            // 0x45 => {} // CMPLINE - Scanline comparison
-            0x46 => {} // DMACONT - DMA Transfer Controller // TODO
+            0x45 => { self.lyc = value }, // CMPLINE - Scanline comparison
             0x47 => { self.bg_palette = value },
             0x48 => { self.sprite_palette_0 = value },
             0x49 => { self.sprite_palette_1 = value },
             0x4A => { self.window_x = value },
             0x4B => { self.window_y = value },
-            _ => panic!("Can't store in GPU yet (0x{:02X})", address)
+            _ => {}
         }
     }
 }
@@ -0,0 +1,76 @@
+#[derive(Copy,Clone,PartialEq,Debug)]
+pub enum Button {
+    Right, Left, Up, Down,
+    A, B, Select, Start,
+}
+
+// http://gbdev.gg8.se/wiki/articles/Joypad_Input
+// Lines are active low: a pressed button reads as 0.
+pub struct Joypad {
+    pub select: u8, // Bits 4 (directions) and 5 (buttons) of P1
+    pub directions: u8,
+    pub buttons: u8,
+
+    pub interrupt: bool,
+}
+
+impl Joypad {
+    pub fn new() -> Joypad {
+        Joypad {
+            select: 0x30,
+            directions: 0x0F,
+            buttons: 0x0F,
+            interrupt: false,
+        }
+    }
+
+    // Input lines P10-P13 as seen through the current selection
+    pub fn lines(&self) -> u8 {
+        let mut lines = 0x0F;
+        if self.select & 0x10 == 0 { lines &= self.directions }
+        if self.select & 0x20 == 0 { lines &= self.buttons }
+        lines
+    }
+
+    pub fn press(&mut self, button: Button) {
+        let before = self.lines();
+        match Joypad::line(button) {
+            (false, bit) => self.directions &= !bit,
+            (true, bit) => self.buttons &= !bit,
+        }
+
+        // The interrupt fires when one of the selected lines goes from high to low
+        if before & !self.lines() != 0 {
+            self.interrupt = true;
+        }
+    }
+
+    pub fn release(&mut self, button: Button) {
+        match Joypad::line(button) {
+            (false, bit) => self.directions |= bit,
+            (true, bit) => self.buttons |= bit,
+        }
+    }
+
+    // Which group the button belongs to (true for buttons) and its bit
+    fn line(button: Button) -> (bool, u8) {
+        match button {
+            Button::Right  => (false, 0x01),
+            Button::Left   => (false, 0x02),
+            Button::Up     => (false, 0x04),
+            Button::Down   => (false, 0x08),
+            Button::A      => (true, 0x01),
+            Button::B      => (true, 0x02),
+            Button::Select => (true, 0x04),
+            Button::Start  => (true, 0x08),
+        }
+    }
+
+    pub fn load(&self) -> u8 {
+        0xC0 | self.select | self.lines()
+    }
+
+    pub fn store(&mut self, value: u8) {
+        self.select = value & 0x30;
+    }
+}
@@ -1,6 +1,16 @@
 pub mod addressing;
+pub mod bus;
 pub mod cartridge;
 pub mod cpu;
+pub mod disasm;
+pub mod error;
 pub mod gpu;
+pub mod joypad;
+pub mod mapper;
 pub mod memory;
+pub mod opcodes;
 pub mod registers;
+pub mod save;
+pub mod serial;
+pub mod timer;
+pub mod trace;
@@ -0,0 +1,77 @@
+use mapper::{rom_byte, Mapper};
+
+// Hudson's MBC1 lookalike with an infrared port in place of the RAM enable.
+// There's nobody on the other side, so the sensor never sees any light.
+pub struct Huc1 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    infrared: bool, // 0xA000-0xBFFF is the IR port instead of RAM
+    pub led: bool, // The IR LED, for anyone curious
+    rom_bank: u8, // 6 bits
+    ram_bank: u8, // 2 bits
+}
+
+impl Huc1 {
+    pub fn new(rom: Vec<u8>, ram_size: usize) -> Huc1 {
+        Huc1 {
+            rom: rom,
+            ram: vec![0; ram_size],
+            infrared: false,
+            led: false,
+            rom_bank: 1,
+            ram_bank: 0,
+        }
+    }
+
+    fn ram_address(&self, address: u16) -> usize {
+        (self.ram_bank as usize * 0x2000 + (address as usize - 0xA000)) % self.ram.len()
+    }
+}
+
+impl Mapper for Huc1 {
+    fn load_rom(&mut self, address: u16) -> u8 {
+        let bank = if address < 0x4000 { 0 } else { self.rom_bank as usize };
+        rom_byte(&self.rom, bank, 0x4000, address as usize & 0x3FFF)
+    }
+
+    fn store_rom(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000...0x1FFF => { self.infrared = value & 0x0F == 0x0E }
+            0x2000...0x3FFF => {
+                self.rom_bank = value & 0x3F;
+                if self.rom_bank == 0 {
+                    self.rom_bank = 1;
+                }
+            }
+            0x4000...0x5FFF => { self.ram_bank = value & 0x03 }
+            _ => {}
+        }
+    }
+
+    fn load_ram(&mut self, address: u16) -> u8 {
+        if self.infrared {
+            0xC0 // Bit 0 set when light is received
+        } else if self.ram.is_empty() {
+            0xFF
+        } else {
+            self.ram[self.ram_address(address)]
+        }
+    }
+
+    fn store_ram(&mut self, address: u16, value: u8) {
+        if self.infrared {
+            self.led = value & 0x01 != 0;
+        } else if !self.ram.is_empty() {
+            let address = self.ram_address(address);
+            self.ram[address] = value;
+        }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+}
@@ -0,0 +1,195 @@
+use std::time::SystemTime;
+
+use mapper::{rom_byte, Mapper};
+
+const CYCLES_PER_SECOND: u64 = 4_194_304;
+const CYCLES_PER_MINUTE: u64 = CYCLES_PER_SECOND * 60;
+
+// The clock counts minutes in the day and days, 12 bits each
+#[derive(Clone,PartialEq,Debug)]
+pub struct Huc3Clock {
+    pub minutes: u16,
+    pub days: u16,
+    cycles: u64, // Towards the next minute
+}
+
+impl Huc3Clock {
+    pub fn new() -> Huc3Clock {
+        Huc3Clock { minutes: 0, days: 0, cycles: 0 }
+    }
+
+    // Whatever the game or a save file says, out of range minutes wrap on the next tick
+    pub fn set(&mut self, minutes: u16, days: u16) {
+        self.minutes = minutes & 0xFFF;
+        self.days = days & 0xFFF;
+        self.cycles = 0;
+    }
+
+    pub fn step(&mut self, cycles: u64) {
+        self.cycles += cycles;
+        while self.cycles >= CYCLES_PER_MINUTE {
+            self.cycles -= CYCLES_PER_MINUTE;
+            self.tick();
+        }
+    }
+
+    fn tick(&mut self) {
+        self.minutes += 1;
+        if self.minutes >= 1440 {
+            self.minutes = 0;
+            self.days = (self.days + 1) & 0xFFF;
+        }
+    }
+
+    // Move the clock forward by a number of seconds, e.g. the time the emulator was closed
+    pub fn advance(&mut self, seconds: u64) {
+        if self.minutes >= 1440 {
+            self.tick();
+        }
+        self.step(seconds % 60 * CYCLES_PER_SECOND);
+
+        let minutes = self.minutes as u64 + seconds / 60;
+        self.minutes = (minutes % 1440) as u16;
+        self.days = ((self.days as u64 + minutes / 1440) & 0xFFF) as u16;
+    }
+
+    pub fn catch_up(&mut self, since: SystemTime) {
+        if let Ok(elapsed) = SystemTime::now().duration_since(since) {
+            self.advance(elapsed.as_secs());
+        }
+    }
+}
+
+// Hudson's mapper with a clock, as in Robopon. 0x0000-0x1FFF selects what
+// 0xA000-0xBFFF talks to: RAM, the clock command and response registers,
+// the clock's ready flag or the infrared port.
+// https://github.com/LIJI32/SameBoy/blob/master/Core/memory.c has the details.
+pub struct Huc3 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    mode: u8,
+    rom_bank: u8, // 7 bits
+    ram_bank: u8, // 2 bits
+
+    // The clock talks through 4 bit commands: read or write a nibble of its
+    // memory, move the access address, or copy the time to and from memory.
+    clock: Huc3Clock,
+    memory: [u8; 0x100], // Nibbles
+    address: u8,
+    command: u8,
+    response: u8,
+}
+
+impl Huc3 {
+    pub fn new(rom: Vec<u8>, ram_size: usize) -> Huc3 {
+        Huc3 {
+            rom: rom,
+            ram: vec![0; ram_size],
+            mode: 0,
+            rom_bank: 1,
+            ram_bank: 0,
+            clock: Huc3Clock::new(),
+            memory: [0; 0x100],
+            address: 0,
+            command: 0,
+            response: 0,
+        }
+    }
+
+    fn ram_address(&self, address: u16) -> usize {
+        (self.ram_bank as usize * 0x2000 + (address as usize - 0xA000)) % self.ram.len()
+    }
+
+    fn clock_command(&mut self, value: u8) {
+        let argument = value & 0x0F;
+        self.command = (value >> 4) & 0x07;
+
+        match self.command {
+            0x1 => {
+                self.response = self.memory[self.address as usize];
+                self.address = self.address.wrapping_add(1);
+            }
+            0x3 => {
+                self.memory[self.address as usize] = argument;
+                self.address = self.address.wrapping_add(1);
+            }
+            0x4 => { self.address = (self.address & 0xF0) | argument }
+            0x5 => { self.address = (self.address & 0x0F) | argument << 4 }
+            0x6 => match argument {
+                0x0 => {
+                    // Time to memory, 3 nibbles of minutes then 3 of days
+                    for i in 0..3 {
+                        self.memory[i] = (self.clock.minutes >> (i * 4)) as u8 & 0x0F;
+                        self.memory[3 + i] = (self.clock.days >> (i * 4)) as u8 & 0x0F;
+                    }
+                }
+                0x1 => {
+                    // Memory to time
+                    let mut minutes = 0;
+                    let mut days = 0;
+                    for i in 0..3 {
+                        minutes |= (self.memory[i] as u16) << (i * 4);
+                        days |= (self.memory[3 + i] as u16) << (i * 4);
+                    }
+                    self.clock.set(minutes, days);
+                }
+                0xE => { self.response = 0x1 } // Status, all good
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+}
+
+impl Mapper for Huc3 {
+    fn load_rom(&mut self, address: u16) -> u8 {
+        let bank = if address < 0x4000 { 0 } else { self.rom_bank as usize };
+        rom_byte(&self.rom, bank, 0x4000, address as usize & 0x3FFF)
+    }
+
+    fn store_rom(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000...0x1FFF => { self.mode = value & 0x0F }
+            0x2000...0x3FFF => { self.rom_bank = value & 0x7F }
+            0x4000...0x5FFF => { self.ram_bank = value & 0x03 }
+            _ => {}
+        }
+    }
+
+    fn load_ram(&mut self, address: u16) -> u8 {
+        match self.mode {
+            0x0 | 0xA if !self.ram.is_empty() => self.ram[self.ram_address(address)],
+            0xC => 0x80 | self.command << 4 | self.response,
+            0xD => 0x01, // Ready for the next command
+            0xE => 0xC0, // Infrared, no light
+            _ => 0xFF,
+        }
+    }
+
+    fn store_ram(&mut self, address: u16, value: u8) {
+        match self.mode {
+            0xA if !self.ram.is_empty() => {
+                let address = self.ram_address(address);
+                self.ram[address] = value;
+            }
+            0xB => self.clock_command(value),
+            _ => {}
+        }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+
+    fn step(&mut self, cycles: u64) {
+        self.clock.step(cycles);
+    }
+
+    fn huc3_clock(&mut self) -> Option<&mut Huc3Clock> {
+        Some(&mut self.clock)
+    }
+}
@@ -0,0 +1,105 @@
+use mapper::{rom_byte, Mapper};
+
+// http://gbdev.gg8.se/wiki/articles/Memory_Bank_Controllers#MBC1_.28max_2MByte_ROM_and.2For_32KByte_RAM.29
+pub struct Mbc1 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    ram_enabled: bool,
+    bank1: u8, // 5 bits, low bits of the ROM bank
+    bank2: u8, // 2 bits, RAM bank or high bits of the ROM bank
+    mode: u8, // 1 makes bank2 apply to 0x0000-0x3FFF and RAM as well
+    // MBC1M multicarts don't wire the top bit of bank1, so bank2 lands one bit lower
+    multicart: bool,
+}
+
+impl Mbc1 {
+    pub fn new(rom: Vec<u8>, ram_size: usize, multicart: bool) -> Mbc1 {
+        Mbc1 {
+            rom: rom,
+            ram: vec![0; ram_size],
+            ram_enabled: false,
+            bank1: 1,
+            bank2: 0,
+            mode: 0,
+            multicart: multicart,
+        }
+    }
+
+    fn rom_bank(&self, high: bool) -> usize {
+        let shift = if self.multicart { 4 } else { 5 };
+        let bank2 = (self.bank2 as usize) << shift;
+
+        if high {
+            let bank1 = if self.multicart { self.bank1 & 0x0F } else { self.bank1 };
+            bank2 | bank1 as usize
+        } else if self.mode == 1 {
+            bank2
+        } else {
+            0
+        }
+    }
+
+    fn ram_address(&self, address: u16) -> usize {
+        let bank = if self.mode == 1 { self.bank2 as usize } else { 0 };
+        (bank * 0x2000 + (address as usize - 0xA000)) % self.ram.len()
+    }
+}
+
+impl Mapper for Mbc1 {
+    fn load_rom(&mut self, address: u16) -> u8 {
+        let bank = self.rom_bank(address >= 0x4000);
+        rom_byte(&self.rom, bank, 0x4000, address as usize & 0x3FFF)
+    }
+
+    fn store_rom(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000...0x1FFF => { self.ram_enabled = value & 0x0F == 0x0A }
+            0x2000...0x3FFF => {
+                // Bank 0 can't be selected there, the whole 5 bits are checked
+                self.bank1 = value & 0x1F;
+                if self.bank1 == 0 {
+                    self.bank1 = 1;
+                }
+            }
+            0x4000...0x5FFF => { self.bank2 = value & 0x03 }
+            _ => { self.mode = value & 0x01 }
+        }
+    }
+
+    fn load_ram(&mut self, address: u16) -> u8 {
+        if !self.ram_enabled || self.ram.is_empty() {
+            return 0xFF;
+        }
+        self.ram[self.ram_address(address)]
+    }
+
+    fn store_ram(&mut self, address: u16, value: u8) {
+        if !self.ram_enabled || self.ram.is_empty() {
+            return;
+        }
+        let address = self.ram_address(address);
+        self.ram[address] = value;
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+}
+
+// MBC1M carts are 8 Mbit and repeat the Nintendo logo at the start of each game,
+// every 16 banks. A plain 1 MB MBC1 game has anything else there.
+pub fn is_multicart(rom: &[u8]) -> bool {
+    if rom.len() != 0x100000 {
+        return false;
+    }
+
+    let logo = &rom[0x104..0x134];
+    (1..4).any(|game| {
+        let start = game * 0x40000 + 0x104;
+        &rom[start..start + 0x30] == logo
+    })
+}
@@ -0,0 +1,65 @@
+use mapper::{rom_byte, Mapper};
+
+// http://gbdev.gg8.se/wiki/articles/Memory_Bank_Controllers#MBC2_.28max_256KByte_ROM_and_512x4_bits_RAM.29
+pub struct Mbc2 {
+    rom: Vec<u8>,
+    ram: [u8; 0x200], // 512 half bytes, built into the MBC
+    ram_enabled: bool,
+    rom_bank: u8, // 4 bits
+}
+
+impl Mbc2 {
+    pub fn new(rom: Vec<u8>) -> Mbc2 {
+        Mbc2 {
+            rom: rom,
+            ram: [0; 0x200],
+            ram_enabled: false,
+            rom_bank: 1,
+        }
+    }
+}
+
+impl Mapper for Mbc2 {
+    fn load_rom(&mut self, address: u16) -> u8 {
+        let bank = if address < 0x4000 { 0 } else { self.rom_bank as usize };
+        rom_byte(&self.rom, bank, 0x4000, address as usize & 0x3FFF)
+    }
+
+    // Both registers live in 0x0000-0x3FFF, address bit 8 picks one
+    fn store_rom(&mut self, address: u16, value: u8) {
+        if address >= 0x4000 {
+            return;
+        }
+
+        if address & 0x100 == 0 {
+            self.ram_enabled = value & 0x0F == 0x0A;
+        } else {
+            self.rom_bank = value & 0x0F;
+            if self.rom_bank == 0 {
+                self.rom_bank = 1;
+            }
+        }
+    }
+
+    // The 512 bytes repeat all over 0xA000-0xBFFF, upper bits read as 1
+    fn load_ram(&mut self, address: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+        self.ram[address as usize & 0x1FF] | 0xF0
+    }
+
+    fn store_ram(&mut self, address: u16, value: u8) {
+        if self.ram_enabled {
+            self.ram[address as usize & 0x1FF] = value & 0x0F;
+        }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+}
@@ -0,0 +1,252 @@
+use std::time::SystemTime;
+
+use mapper::{rom_byte, Mapper};
+
+// The RTC counts real seconds, whatever the CPU speed
+const CYCLES_PER_SECOND: u64 = 4_194_304;
+
+// http://gbdev.gg8.se/wiki/articles/Memory_Bank_Controllers#MBC3_.28max_2MByte_ROM_and.2For_32KByte_RAM_and_Timer.29
+// The registers are 0x08 to 0x0C: seconds, minutes, hours, low 8 bits of the day
+// counter, then day bit 8, halt (bit 6) and day counter carry (bit 7).
+#[derive(Clone,PartialEq,Debug)]
+pub struct Rtc {
+    pub seconds: u8,
+    pub minutes: u8,
+    pub hours: u8,
+    pub days: u16, // 9 bits
+    pub halt: bool,
+    pub carry: bool, // Set when the day counter overflows, until cleared by the game
+    pub latched: [u8; 5], // What the game reads, copied from the counters on latch
+    cycles: u64, // Towards the next second
+}
+
+impl Rtc {
+    pub fn new() -> Rtc {
+        Rtc {
+            seconds: 0,
+            minutes: 0,
+            hours: 0,
+            days: 0,
+            halt: false,
+            carry: false,
+            latched: [0; 5],
+            cycles: 0,
+        }
+    }
+
+    // The live counters, in register order
+    pub fn registers(&self) -> [u8; 5] {
+        [
+            self.seconds,
+            self.minutes,
+            self.hours,
+            self.days as u8,
+            (self.days >> 8) as u8 | (self.halt as u8) << 6 | (self.carry as u8) << 7,
+        ]
+    }
+
+    pub fn latch(&mut self) {
+        self.latched = self.registers();
+    }
+
+    pub fn load(&self, register: u8) -> u8 {
+        let masks = [0x3F, 0x3F, 0x1F, 0xFF, 0xC1];
+        let index = (register - 0x08) as usize;
+        self.latched[index] & masks[index]
+    }
+
+    // Writes go to the counters, and show up without waiting for a latch
+    pub fn store(&mut self, register: u8, value: u8) {
+        match register {
+            0x08 => {
+                self.seconds = value & 0x3F;
+                self.cycles = 0; // Also resets the sub-second divider
+            }
+            0x09 => { self.minutes = value & 0x3F }
+            0x0A => { self.hours = value & 0x1F }
+            0x0B => { self.days = (self.days & 0x100) | value as u16 }
+            _ => {
+                self.days = (self.days & 0xFF) | ((value & 0x01) as u16) << 8;
+                self.halt = value & 0x40 != 0;
+                self.carry = value & 0x80 != 0;
+            }
+        }
+        self.latched[(register - 0x08) as usize] = value;
+    }
+
+    pub fn step(&mut self, cycles: u64) {
+        if self.halt {
+            return;
+        }
+
+        self.cycles += cycles;
+        while self.cycles >= CYCLES_PER_SECOND {
+            self.cycles -= CYCLES_PER_SECOND;
+            self.tick();
+        }
+    }
+
+    // Out of range values count up to the top of their bits and wrap to 0
+    // without carrying into the next counter.
+    fn tick(&mut self) {
+        self.seconds = (self.seconds + 1) & 0x3F;
+        if self.seconds != 60 {
+            return;
+        }
+        self.seconds = 0;
+
+        self.minutes = (self.minutes + 1) & 0x3F;
+        if self.minutes != 60 {
+            return;
+        }
+        self.minutes = 0;
+
+        self.hours = (self.hours + 1) & 0x1F;
+        if self.hours != 24 {
+            return;
+        }
+        self.hours = 0;
+
+        self.days += 1;
+        if self.days == 512 {
+            self.days = 0;
+            self.carry = true;
+        }
+    }
+
+    // Move the clock forward by a number of seconds, e.g. the time the emulator was closed
+    pub fn advance(&mut self, mut seconds: u64) {
+        if self.halt {
+            return;
+        }
+
+        // Get the counters back in range one second at a time, then do the rest at once
+        while seconds > 0 && (self.seconds > 59 || self.minutes > 59 || self.hours > 23) {
+            self.tick();
+            seconds -= 1;
+        }
+
+        let total = self.seconds as u64 + self.minutes as u64 * 60 + self.hours as u64 * 3600
+            + self.days as u64 * 86400 + seconds;
+        let days = total / 86400;
+        self.seconds = (total % 60) as u8;
+        self.minutes = (total / 60 % 60) as u8;
+        self.hours = (total / 3600 % 24) as u8;
+        self.days = (days % 512) as u16;
+        if days >= 512 {
+            self.carry = true;
+        }
+    }
+
+    // Catch up with the host clock since a point in time, does nothing if the clock went backwards
+    pub fn catch_up(&mut self, since: SystemTime) {
+        if let Ok(elapsed) = SystemTime::now().duration_since(since) {
+            self.advance(elapsed.as_secs());
+        }
+    }
+}
+
+pub struct Mbc3 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    ram_enabled: bool, // Also enables the RTC registers
+    rom_bank: u8, // 7 bits
+    ram_bank: u8, // RAM bank 0-3 or RTC register 0x08-0x0C
+    latch: u8, // Last value written to 0x6000-0x7FFF, latching is 0 then 1
+    rtc: Option<Rtc>,
+}
+
+impl Mbc3 {
+    pub fn new(rom: Vec<u8>, ram_size: usize, rtc: bool) -> Mbc3 {
+        Mbc3 {
+            rom: rom,
+            ram: vec![0; ram_size],
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            latch: 0xFF,
+            rtc: if rtc { Some(Rtc::new()) } else { None },
+        }
+    }
+
+    fn ram_address(&self, address: u16) -> usize {
+        (self.ram_bank as usize * 0x2000 + (address as usize - 0xA000)) % self.ram.len()
+    }
+}
+
+impl Mapper for Mbc3 {
+    fn load_rom(&mut self, address: u16) -> u8 {
+        let bank = if address < 0x4000 { 0 } else { self.rom_bank as usize };
+        rom_byte(&self.rom, bank, 0x4000, address as usize & 0x3FFF)
+    }
+
+    fn store_rom(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000...0x1FFF => { self.ram_enabled = value & 0x0F == 0x0A }
+            0x2000...0x3FFF => {
+                self.rom_bank = value & 0x7F;
+                if self.rom_bank == 0 {
+                    self.rom_bank = 1;
+                }
+            }
+            0x4000...0x5FFF => { self.ram_bank = value & 0x0F }
+            _ => {
+                if self.latch == 0x00 && value == 0x01 {
+                    if let Some(ref mut rtc) = self.rtc {
+                        rtc.latch();
+                    }
+                }
+                self.latch = value;
+            }
+        }
+    }
+
+    fn load_ram(&mut self, address: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+
+        match (self.ram_bank, self.rtc.as_ref()) {
+            (0x00...0x03, _) if !self.ram.is_empty() => self.ram[self.ram_address(address)],
+            (0x08...0x0C, Some(rtc)) => rtc.load(self.ram_bank),
+            _ => 0xFF,
+        }
+    }
+
+    fn store_ram(&mut self, address: u16, value: u8) {
+        if !self.ram_enabled {
+            return;
+        }
+
+        match self.ram_bank {
+            0x00...0x03 if !self.ram.is_empty() => {
+                let address = self.ram_address(address);
+                self.ram[address] = value;
+            }
+            0x08...0x0C => {
+                if let Some(ref mut rtc) = self.rtc {
+                    rtc.store(self.ram_bank, value);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+
+    fn step(&mut self, cycles: u64) {
+        if let Some(ref mut rtc) = self.rtc {
+            rtc.step(cycles);
+        }
+    }
+
+    fn rtc(&mut self) -> Option<&mut Rtc> {
+        self.rtc.as_mut()
+    }
+}
@@ -0,0 +1,97 @@
+use mapper::{rom_byte, Mapper};
+
+// http://gbdev.gg8.se/wiki/articles/Memory_Bank_Controllers#MBC5_.28max_8MByte_ROM_and.2For_128KByte_RAM.29
+pub struct Mbc5 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    ram_enabled: bool,
+    rom_bank: u16, // 9 bits, bank 0 can be mapped at 0x4000 too
+    ram_bank: u8, // 4 bits, 3 on rumble carts
+    // Rumble carts wire the motor to bit 3 of the RAM bank
+    rumble_wired: bool,
+    rumble: bool,
+    rumble_changed: bool, // Since the frontend last asked
+}
+
+impl Mbc5 {
+    pub fn new(rom: Vec<u8>, ram_size: usize, rumble: bool) -> Mbc5 {
+        Mbc5 {
+            rom: rom,
+            ram: vec![0; ram_size],
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            rumble_wired: rumble,
+            rumble: false,
+            rumble_changed: false,
+        }
+    }
+
+    fn ram_address(&self, address: u16) -> usize {
+        (self.ram_bank as usize * 0x2000 + (address as usize - 0xA000)) % self.ram.len()
+    }
+}
+
+impl Mapper for Mbc5 {
+    fn load_rom(&mut self, address: u16) -> u8 {
+        let bank = if address < 0x4000 { 0 } else { self.rom_bank as usize };
+        rom_byte(&self.rom, bank, 0x4000, address as usize & 0x3FFF)
+    }
+
+    fn store_rom(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000...0x1FFF => { self.ram_enabled = value == 0x0A }
+            0x2000...0x2FFF => { self.rom_bank = (self.rom_bank & 0x100) | value as u16 }
+            0x3000...0x3FFF => { self.rom_bank = (self.rom_bank & 0xFF) | ((value & 0x01) as u16) << 8 }
+            0x4000...0x5FFF => {
+                if self.rumble_wired {
+                    self.ram_bank = value & 0x07;
+                    let rumble = value & 0x08 != 0;
+                    if rumble != self.rumble {
+                        self.rumble = rumble;
+                        self.rumble_changed = true;
+                    }
+                } else {
+                    self.ram_bank = value & 0x0F;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn load_ram(&mut self, address: u16) -> u8 {
+        if !self.ram_enabled || self.ram.is_empty() {
+            return 0xFF;
+        }
+        self.ram[self.ram_address(address)]
+    }
+
+    fn store_ram(&mut self, address: u16, value: u8) {
+        if !self.ram_enabled || self.ram.is_empty() {
+            return;
+        }
+        let address = self.ram_address(address);
+        self.ram[address] = value;
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+
+    fn rumble(&self) -> bool {
+        self.rumble
+    }
+
+    fn take_rumble_change(&mut self) -> Option<bool> {
+        if self.rumble_changed {
+            self.rumble_changed = false;
+            Some(self.rumble)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use mapper::{rom_byte, Mapper};
+
+// What the accelerometer reads when flat, and how much 1 g adds to that
+const ACCELEROMETER_CENTER: u16 = 0x81D0;
+const ACCELEROMETER_G: f32 = 112.0;
+
+// The 93LC56 serial EEPROM, 128 words of 16 bits. The cart bit bangs it
+// through one register: chip select, clock, data in and data out.
+// http://ww1.microchip.com/downloads/en/DeviceDoc/21794F.pdf
+enum EepromState {
+    Idle, // Waiting for the start bit
+    Command { bits: u8, value: u16 }, // 2 bits of opcode and 8 of address
+    Read { word: u16 },
+    Write { address: Option<u8>, bits: u8, value: u16 }, // None writes every word
+    Done,
+}
+
+pub struct Eeprom {
+    pub data: Vec<u8>, // Words in little endian
+    state: EepromState,
+    write_enabled: bool,
+    select: bool,
+    clock: bool,
+    data_in: bool,
+    data_out: bool,
+}
+
+impl Eeprom {
+    pub fn new() -> Eeprom {
+        Eeprom {
+            data: vec![0xFF; 0x100],
+            state: EepromState::Idle,
+            write_enabled: false,
+            select: false,
+            clock: false,
+            data_in: false,
+            data_out: true,
+        }
+    }
+
+    fn word(&self, address: u8) -> u16 {
+        let index = (address & 0x7F) as usize * 2;
+        self.data[index] as u16 | (self.data[index + 1] as u16) << 8
+    }
+
+    fn set_word(&mut self, address: u8, value: u16) {
+        let index = (address & 0x7F) as usize * 2;
+        self.data[index] = value as u8;
+        self.data[index + 1] = (value >> 8) as u8;
+    }
+
+    pub fn load(&self) -> u8 {
+        (self.select as u8) << 7 | (self.clock as u8) << 6 | (self.data_in as u8) << 1 | self.data_out as u8
+    }
+
+    pub fn store(&mut self, value: u8) {
+        let rising = !self.clock && value & 0x40 != 0;
+        self.select = value & 0x80 != 0;
+        self.clock = value & 0x40 != 0;
+        self.data_in = value & 0x02 != 0;
+
+        if !self.select {
+            self.state = EepromState::Idle;
+            self.data_out = true;
+        } else if rising {
+            self.shift(self.data_in as u16);
+        }
+    }
+
+    // One bit in on each rising edge of the clock
+    fn shift(&mut self, bit: u16) {
+        self.state = match self.state {
+            EepromState::Idle if bit == 1 => EepromState::Command { bits: 0, value: 0 },
+            EepromState::Idle => EepromState::Idle,
+            EepromState::Command { bits: 9, value } => self.command(value << 1 | bit),
+            EepromState::Command { bits, value } => EepromState::Command { bits: bits + 1, value: value << 1 | bit },
+            EepromState::Read { word } => {
+                self.data_out = word & 0x8000 != 0;
+                EepromState::Read { word: word << 1 }
+            }
+            EepromState::Write { address, bits: 15, value } => {
+                let value = value << 1 | bit;
+                if self.write_enabled {
+                    match address {
+                        Some(address) => self.set_word(address, value),
+                        None => for address in 0..0x80 { self.set_word(address, value) },
+                    }
+                }
+                self.data_out = true; // Ready
+                EepromState::Done
+            }
+            EepromState::Write { address, bits, value } => {
+                EepromState::Write { address: address, bits: bits + 1, value: value << 1 | bit }
+            }
+            EepromState::Done => EepromState::Done,
+        }
+    }
+
+    fn command(&mut self, command: u16) -> EepromState {
+        let address = command as u8;
+        match command >> 8 {
+            0b10 => {
+                self.data_out = false; // Dummy bit before the data
+                EepromState::Read { word: self.word(address) }
+            }
+            0b01 => EepromState::Write { address: Some(address), bits: 0, value: 0 },
+            0b11 => {
+                if self.write_enabled {
+                    self.set_word(address, 0xFFFF);
+                }
+                EepromState::Done
+            }
+            _ => match address >> 6 {
+                0b11 => { self.write_enabled = true; EepromState::Done }
+                0b00 => { self.write_enabled = false; EepromState::Done }
+                0b10 => {
+                    if self.write_enabled {
+                        for byte in self.data.iter_mut() {
+                            *byte = 0xFF;
+                        }
+                    }
+                    EepromState::Done
+                }
+                _ => EepromState::Write { address: None, bits: 0, value: 0 },
+            },
+        }
+    }
+}
+
+// http://gbdev.gg8.se/wiki/articles/MBC7
+// RAM is replaced by an accelerometer and the EEPROM, both in 0xA000-0xAFFF
+// with bits 4-7 of the address picking the register.
+pub struct Mbc7 {
+    rom: Vec<u8>,
+    pub eeprom: Eeprom,
+    ram_enabled: (bool, bool), // Both need to be set
+    rom_bank: u8, // 7 bits
+    tilt: (f32, f32),
+    accelerometer: (u16, u16), // Latched x and y
+    latch_ready: bool, // 0x55 was written, waiting for 0xAA
+}
+
+impl Mbc7 {
+    pub fn new(rom: Vec<u8>) -> Mbc7 {
+        Mbc7 {
+            rom: rom,
+            eeprom: Eeprom::new(),
+            ram_enabled: (false, false),
+            rom_bank: 1,
+            tilt: (0.0, 0.0),
+            accelerometer: (0x8000, 0x8000),
+            latch_ready: false,
+        }
+    }
+
+    fn accelerometer(tilt: f32) -> u16 {
+        (ACCELEROMETER_CENTER as f32 + tilt * ACCELEROMETER_G) as u16
+    }
+}
+
+impl Mapper for Mbc7 {
+    fn load_rom(&mut self, address: u16) -> u8 {
+        let bank = if address < 0x4000 { 0 } else { self.rom_bank as usize };
+        rom_byte(&self.rom, bank, 0x4000, address as usize & 0x3FFF)
+    }
+
+    fn store_rom(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000...0x1FFF => { self.ram_enabled.0 = value == 0x0A }
+            0x2000...0x3FFF => { self.rom_bank = value & 0x7F }
+            0x4000...0x5FFF => { self.ram_enabled.1 = value == 0x40 }
+            _ => {}
+        }
+    }
+
+    fn load_ram(&mut self, address: u16) -> u8 {
+        if self.ram_enabled != (true, true) || address >= 0xB000 {
+            return 0xFF;
+        }
+
+        let (x, y) = self.accelerometer;
+        match (address >> 4) & 0x0F {
+            0x2 => x as u8,
+            0x3 => (x >> 8) as u8,
+            0x4 => y as u8,
+            0x5 => (y >> 8) as u8,
+            0x6 => 0x00, // No z axis
+            0x8 => self.eeprom.load(),
+            _ => 0xFF,
+        }
+    }
+
+    fn store_ram(&mut self, address: u16, value: u8) {
+        if self.ram_enabled != (true, true) || address >= 0xB000 {
+            return;
+        }
+
+        match (address >> 4) & 0x0F {
+            0x0 if value == 0x55 => {
+                self.accelerometer = (0x8000, 0x8000);
+                self.latch_ready = true;
+            }
+            0x1 if value == 0xAA && self.latch_ready => {
+                self.accelerometer = (Mbc7::accelerometer(self.tilt.0), Mbc7::accelerometer(self.tilt.1));
+                self.latch_ready = false;
+            }
+            0x8 => self.eeprom.store(value),
+            _ => {}
+        }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.eeprom.data
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.eeprom.data
+    }
+
+    fn tilt(&mut self, x: f32, y: f32) {
+        self.tilt = (x, y);
+    }
+}
@@ -0,0 +1,137 @@
+use cartridge::{Header, LOGO};
+use mapper::{rom_byte, Mapper};
+
+// Multicart mapper. It starts with the menu, in the last 32 kB of the ROM, and
+// the MBC1 style registers also set which part of the ROM the chosen game gets.
+// Setting bit 6 of 0x0000-0x1FFF locks that in and starts the game, which then
+// only sees its own banks.
+// http://gbdev.gg8.se/wiki/articles/MMM01
+pub struct Mmm01 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    locked: bool,
+    ram_enabled: bool,
+    rom_bank: u8, // 5 bits, MBC1 style
+    rom_bank_mid: u8, // 2 bits above rom_bank, set before locking
+    rom_bank_high: u8, // 2 bits above that
+    rom_mask: u8, // rom_bank bits the game can't change, set before locking
+    ram_bank: u8, // 2 bits
+    ram_bank_high: u8, // 2 bits above ram_bank, set before locking
+    mode: u8,
+}
+
+impl Mmm01 {
+    pub fn new(rom: Vec<u8>, ram_size: usize) -> Mmm01 {
+        Mmm01 {
+            rom: rom,
+            ram: vec![0; ram_size],
+            locked: false,
+            ram_enabled: false,
+            rom_bank: 0,
+            rom_bank_mid: 0,
+            rom_bank_high: 0,
+            rom_mask: 0,
+            ram_bank: 0,
+            ram_bank_high: 0,
+            mode: 0,
+        }
+    }
+
+    fn rom_bank(&self, high: bool) -> usize {
+        let banks = self.rom.len() / 0x4000;
+        if !self.locked {
+            // The menu
+            return if high { banks.saturating_sub(1) } else { banks.saturating_sub(2) };
+        }
+
+        let base = (self.rom_bank_high as usize) << 7 | (self.rom_bank_mid as usize) << 5;
+        if high {
+            let bank = if self.rom_bank & !self.rom_mask == 0 { self.rom_bank | 1 } else { self.rom_bank };
+            base | bank as usize
+        } else {
+            // The first bank of the game, which the masked bits picked
+            base | (self.rom_bank & self.rom_mask) as usize
+        }
+    }
+
+    fn ram_address(&self, address: u16) -> usize {
+        let bank = (self.ram_bank_high << 2 | if self.mode == 1 { self.ram_bank } else { 0 }) as usize;
+        (bank * 0x2000 + (address as usize - 0xA000)) % self.ram.len()
+    }
+}
+
+impl Mapper for Mmm01 {
+    fn load_rom(&mut self, address: u16) -> u8 {
+        let bank = self.rom_bank(address >= 0x4000);
+        rom_byte(&self.rom, bank, 0x4000, address as usize & 0x3FFF)
+    }
+
+    fn store_rom(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000...0x1FFF => {
+                self.ram_enabled = value & 0x0F == 0x0A;
+                if value & 0x40 != 0 {
+                    self.locked = true;
+                }
+            }
+            0x2000...0x3FFF => {
+                let mask = if self.locked { self.rom_mask } else { 0 };
+                self.rom_bank = (self.rom_bank & mask) | (value & 0x1F & !mask);
+                if !self.locked {
+                    self.rom_bank_mid = (value >> 5) & 0x03;
+                }
+            }
+            0x4000...0x5FFF => {
+                self.ram_bank = value & 0x03;
+                if !self.locked {
+                    self.ram_bank_high = (value >> 2) & 0x03;
+                    self.rom_bank_high = (value >> 4) & 0x03;
+                }
+            }
+            _ => {
+                self.mode = value & 0x01;
+                if !self.locked {
+                    // Bits 2-5 cover rom_bank bits 1-4
+                    self.rom_mask = (value >> 1) & 0x1E;
+                }
+            }
+        }
+    }
+
+    fn load_ram(&mut self, address: u16) -> u8 {
+        if !self.ram_enabled || self.ram.is_empty() {
+            return 0xFF;
+        }
+        self.ram[self.ram_address(address)]
+    }
+
+    fn store_ram(&mut self, address: u16, value: u8) {
+        if !self.ram_enabled || self.ram.is_empty() {
+            return;
+        }
+        let address = self.ram_address(address);
+        self.ram[address] = value;
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+}
+
+// The header that counts is the menu's, at the end of the ROM. Any big ROM has
+// some byte there, so it has to be a whole valid header to be taken for a menu.
+pub fn is_mmm01(rom: &[u8]) -> bool {
+    if rom.len() < 0x10000 {
+        return false;
+    }
+
+    let menu = &rom[rom.len() - 0x8000..];
+    let cartridge_type = menu[0x147];
+    menu[0x104..0x134] == LOGO[..]
+        && menu[0x14D] == Header::checksum(menu)
+        && cartridge_type >= 0x0B && cartridge_type <= 0x0D
+}
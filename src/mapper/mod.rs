@@ -0,0 +1,223 @@
+use cartridge::{Cartridge, Header};
+
+pub mod huc1;
+pub mod huc3;
+pub mod mbc1;
+pub mod mbc2;
+pub mod mbc3;
+pub mod mbc5;
+pub mod mbc7;
+pub mod mmm01;
+pub mod unlicensed;
+
+pub use self::huc1::Huc1;
+pub use self::huc3::{Huc3, Huc3Clock};
+pub use self::mbc1::Mbc1;
+pub use self::mbc2::Mbc2;
+pub use self::mbc3::{Mbc3, Rtc};
+pub use self::mbc5::Mbc5;
+pub use self::mbc7::Mbc7;
+pub use self::mmm01::Mmm01;
+pub use self::unlicensed::{WisdomTree, Sachen, M161};
+
+// The chip on the cartridge deciding what the CPU sees at 0x0000-0x7FFF
+// (ROM) and 0xA000-0xBFFF (external RAM). Addresses are the CPU's.
+pub trait Mapper {
+    fn load_rom(&mut self, address: u16) -> u8;
+    // Writes to the ROM area go to the mapper registers
+    fn store_rom(&mut self, address: u16, value: u8);
+
+    fn load_ram(&mut self, address: u16) -> u8;
+    fn store_ram(&mut self, address: u16, value: u8);
+
+    // What the battery keeps, external RAM or whatever stands for it
+    fn ram(&self) -> &[u8] { &[] }
+    fn ram_mut(&mut self) -> &mut [u8] { &mut [] }
+
+    // Advance anything running on its own, in clock cycles at normal speed
+    fn step(&mut self, _cycles: u64) {}
+
+    // MBC3's real-time clock, for the carts that have one
+    fn rtc(&mut self) -> Option<&mut Rtc> { None }
+
+    // HuC3's clock, which only counts minutes and days
+    fn huc3_clock(&mut self) -> Option<&mut Huc3Clock> { None }
+
+    // Whether the rumble motor is on
+    fn rumble(&self) -> bool { false }
+
+    // The new state of the rumble motor if it was switched on or off since the
+    // last call, for frontends to forward to a gamepad
+    fn take_rumble_change(&mut self) -> Option<bool> { None }
+
+    // How the cartridge is tilted, in g, for the ones with an accelerometer.
+    // Positive x is right and positive y is down.
+    fn tilt(&mut self, _x: f32, _y: f32) {}
+}
+
+// Banks wrap around like the address lines do. ROMs that don't even fill a
+// bank read 0xFF past their end.
+fn rom_byte(rom: &[u8], bank: usize, size: usize, offset: usize) -> u8 {
+    let banks = (rom.len() / size).max(1);
+    rom.get((bank % banks) * size + offset).cloned().unwrap_or(0xFF)
+}
+
+// Every mapper we know about
+#[derive(Copy,Clone,PartialEq,Debug)]
+pub enum Kind {
+    RomOnly,
+    Mbc1,
+    Mbc1Multicart,
+    Mbc2,
+    Mbc3,
+    Mbc5,
+    Mbc7,
+    Mmm01,
+    Huc1,
+    Huc3,
+    WisdomTree,
+    Sachen,
+    M161,
+}
+
+// Names for forcing a mapper, e.g. from the command line
+const NAMES: [(Kind, &str); 13] = [
+    (Kind::RomOnly, "rom"),
+    (Kind::Mbc1, "mbc1"),
+    (Kind::Mbc1Multicart, "mbc1m"),
+    (Kind::Mbc2, "mbc2"),
+    (Kind::Mbc3, "mbc3"),
+    (Kind::Mbc5, "mbc5"),
+    (Kind::Mbc7, "mbc7"),
+    (Kind::Mmm01, "mmm01"),
+    (Kind::Huc1, "huc1"),
+    (Kind::Huc3, "huc3"),
+    (Kind::WisdomTree, "wisdom-tree"),
+    (Kind::Sachen, "sachen"),
+    (Kind::M161, "m161"),
+];
+
+impl Kind {
+    // Picks the mapper from the cartridge type byte, None if we don't support it
+    pub fn detect(cartridge: &Cartridge) -> Option<Kind> {
+        if let Some(kind) = Kind::detect_unlicensed(&cartridge.rom) {
+            return Some(kind);
+        }
+        if mmm01::is_mmm01(&cartridge.rom) {
+            return Some(Kind::Mmm01);
+        }
+
+        match cartridge.header.cartridge_type {
+            0x00 | 0x08 | 0x09 => Some(Kind::RomOnly),
+            0x01...0x03 if mbc1::is_multicart(&cartridge.rom) => Some(Kind::Mbc1Multicart),
+            0x01...0x03 => Some(Kind::Mbc1),
+            0x05 | 0x06 => Some(Kind::Mbc2),
+            0x0B...0x0D => Some(Kind::Mmm01),
+            0x0F...0x13 => Some(Kind::Mbc3),
+            0x19...0x1E => Some(Kind::Mbc5),
+            0x22 => Some(Kind::Mbc7),
+            0xFE => Some(Kind::Huc3),
+            0xFF => Some(Kind::Huc1),
+            _ => None,
+        }
+    }
+
+    // Looks for the unlicensed mappers, which can't be trusted with a header.
+    // Works on the raw ROM as the header might not even pass the checks.
+    pub fn detect_unlicensed(rom: &[u8]) -> Option<Kind> {
+        if rom.len() < 0x150 {
+            None
+        } else if unlicensed::is_wisdom_tree(rom) {
+            Some(Kind::WisdomTree)
+        } else if unlicensed::is_sachen(rom) {
+            Some(Kind::Sachen)
+        } else if unlicensed::is_m161(rom) {
+            Some(Kind::M161)
+        } else {
+            None
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Kind> {
+        NAMES.iter().find(|&&(_, n)| n == name).map(|&(kind, _)| kind)
+    }
+
+    pub fn name(&self) -> &'static str {
+        NAMES.iter().find(|&&(kind, _)| kind == *self).map(|&(_, name)| name).unwrap()
+    }
+
+    pub fn names() -> Vec<&'static str> {
+        NAMES.iter().map(|&(_, name)| name).collect()
+    }
+}
+
+pub fn new(kind: Kind, cartridge: Cartridge) -> Box<dyn Mapper> {
+    let ram_size = cartridge.header.ram_size().unwrap_or(0);
+    let cartridge_type = cartridge.header.cartridge_type;
+    match kind {
+        Kind::RomOnly => Box::new(RomOnly::new(cartridge.rom, ram_size)),
+        Kind::Mbc1 => Box::new(Mbc1::new(cartridge.rom, ram_size, false)),
+        Kind::Mbc1Multicart => Box::new(Mbc1::new(cartridge.rom, ram_size, true)),
+        Kind::Mbc2 => Box::new(Mbc2::new(cartridge.rom)),
+        Kind::Mbc3 => {
+            let rtc = cartridge_type == 0x0F || cartridge_type == 0x10;
+            Box::new(Mbc3::new(cartridge.rom, ram_size, rtc))
+        }
+        Kind::Mbc5 => {
+            let rumble = cartridge_type >= 0x1C;
+            Box::new(Mbc5::new(cartridge.rom, ram_size, rumble))
+        }
+        Kind::Mbc7 => Box::new(Mbc7::new(cartridge.rom)),
+        Kind::Mmm01 => {
+            // The menu's header is the one that knows about the RAM
+            let menu = cartridge.rom.len().saturating_sub(0x8000);
+            let ram_size = Header::parse(&cartridge.rom[menu..]).ok()
+                .and_then(|header| header.ram_size())
+                .unwrap_or(ram_size);
+            Box::new(Mmm01::new(cartridge.rom, ram_size))
+        }
+        Kind::Huc1 => Box::new(Huc1::new(cartridge.rom, ram_size)),
+        Kind::Huc3 => Box::new(Huc3::new(cartridge.rom, ram_size)),
+        Kind::WisdomTree => Box::new(WisdomTree::new(cartridge.rom)),
+        Kind::Sachen => Box::new(Sachen::new(cartridge.rom)),
+        Kind::M161 => Box::new(M161::new(cartridge.rom)),
+    }
+}
+
+// 32 kB of ROM and maybe 8 kB of RAM, no banking
+pub struct RomOnly {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+}
+
+impl RomOnly {
+    pub fn new(rom: Vec<u8>, ram_size: usize) -> RomOnly {
+        RomOnly { rom: rom, ram: vec![0; ram_size] }
+    }
+}
+
+impl Mapper for RomOnly {
+    fn load_rom(&mut self, address: u16) -> u8 {
+        self.rom.get(address as usize).cloned().unwrap_or(0xFF)
+    }
+
+    fn store_rom(&mut self, _address: u16, _value: u8) {}
+
+    fn load_ram(&mut self, address: u16) -> u8 {
+        self.ram.get(address as usize - 0xA000).cloned().unwrap_or(0xFF)
+    }
+
+    fn store_ram(&mut self, address: u16, value: u8) {
+        if let Some(byte) = self.ram.get_mut(address as usize - 0xA000) {
+            *byte = value;
+        }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+}
@@ -0,0 +1,141 @@
+use cartridge::LOGO;
+use mapper::{rom_byte, Mapper};
+
+// Unlicensed carts often have bogus headers, so these are found by looking at
+// the ROM itself rather than at the cartridge type byte.
+
+// Wisdom Tree switches all 32 kB at once. The bank is the low byte of the
+// address written to in 0x0000-0x3FFF, whatever the value.
+pub struct WisdomTree {
+    rom: Vec<u8>,
+    bank: u8,
+}
+
+impl WisdomTree {
+    pub fn new(rom: Vec<u8>) -> WisdomTree {
+        WisdomTree { rom: rom, bank: 0 }
+    }
+}
+
+impl Mapper for WisdomTree {
+    fn load_rom(&mut self, address: u16) -> u8 {
+        rom_byte(&self.rom, self.bank as usize, 0x8000, address as usize)
+    }
+
+    fn store_rom(&mut self, address: u16, _value: u8) {
+        if address < 0x4000 {
+            self.bank = address as u8;
+        }
+    }
+
+    fn load_ram(&mut self, _address: u16) -> u8 { 0xFF }
+    fn store_ram(&mut self, _address: u16, _value: u8) {}
+}
+
+// Sachen's MMC1 (nothing to do with Nintendo's MBC1). The ROM bank can be
+// pinned in part to a base bank through a mask, which is how their multicarts
+// split the ROM between games. The base and mask can only be changed while
+// bits 4 and 5 of the ROM bank are set.
+//
+// On hardware the header area is scrambled until the boot ROM is done with it,
+// which is how the carts get past the logo check. We don't run the boot ROM so
+// the cart starts unlocked. The MMC2 only adds a second stage to that dance and
+// banks the same way.
+// http://gbdev.gg8.se/wiki/articles/Sachen_MMC1
+pub struct Sachen {
+    rom: Vec<u8>,
+    bank: u8, // As written, before the mask
+    base: u8,
+    mask: u8, // Bits set come from the base bank
+}
+
+impl Sachen {
+    pub fn new(rom: Vec<u8>) -> Sachen {
+        Sachen { rom: rom, bank: 1, base: 0, mask: 0 }
+    }
+
+    fn unlocked(&self) -> bool {
+        self.bank & 0x30 == 0x30
+    }
+}
+
+impl Mapper for Sachen {
+    fn load_rom(&mut self, address: u16) -> u8 {
+        let bank = if address < 0x4000 {
+            self.base & self.mask
+        } else {
+            (self.bank & !self.mask) | (self.base & self.mask)
+        };
+        rom_byte(&self.rom, bank as usize, 0x4000, address as usize & 0x3FFF)
+    }
+
+    fn store_rom(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000...0x1FFF if self.unlocked() => { self.base = value }
+            0x2000...0x3FFF => { self.bank = if value == 0 { 1 } else { value } }
+            0x4000...0x5FFF if self.unlocked() => { self.mask = value }
+            _ => {}
+        }
+    }
+
+    fn load_ram(&mut self, _address: u16) -> u8 { 0xFF }
+    fn store_ram(&mut self, _address: u16, _value: u8) {}
+}
+
+// The M161 in bootleg multicarts picks one 32 kB game on the first write to
+// the ROM area, and then ignores everything until reset.
+pub struct M161 {
+    rom: Vec<u8>,
+    bank: u8,
+    locked: bool,
+}
+
+impl M161 {
+    pub fn new(rom: Vec<u8>) -> M161 {
+        M161 { rom: rom, bank: 0, locked: false }
+    }
+}
+
+impl Mapper for M161 {
+    fn load_rom(&mut self, address: u16) -> u8 {
+        rom_byte(&self.rom, self.bank as usize, 0x8000, address as usize)
+    }
+
+    fn store_rom(&mut self, _address: u16, value: u8) {
+        if !self.locked {
+            self.bank = value & 0x07;
+            self.locked = true;
+        }
+    }
+
+    fn load_ram(&mut self, _address: u16) -> u8 { 0xFF }
+    fn store_ram(&mut self, _address: u16, _value: u8) {}
+}
+
+pub fn is_wisdom_tree(rom: &[u8]) -> bool {
+    let title = &rom[0x134..0x143];
+    if title.starts_with(b"WISDOM TREE") || title.starts_with(b"WISDOM\0TREE") {
+        return true;
+    }
+
+    // Some only say so in the code, with a type byte of 0 and too much ROM for it
+    rom[0x147] == 0x00 && rom.len() > 0x8000 &&
+        rom[..0x4000].windows(11).any(|window| window == b"WISDOM TREE")
+}
+
+// The header scrambling swaps address lines A0 with A6 and A1 with A4
+fn scramble(address: usize) -> usize {
+    let bit = |n: usize| (address >> n) & 1;
+    address & !0x53 | bit(6) | bit(4) << 1 | bit(1) << 4 | bit(0) << 6
+}
+
+// Sachen carts have their own logo at 0x104, and the Nintendo one where the
+// boot ROM reads it through the scrambling
+pub fn is_sachen(rom: &[u8]) -> bool {
+    rom.len() >= 0x180 && rom[0x104..0x134] != LOGO[..] &&
+        (0..LOGO.len()).all(|i| rom[scramble(0x104 + i)] == LOGO[i])
+}
+
+pub fn is_m161(rom: &[u8]) -> bool {
+    rom[0x147] == 0x10 && rom[0x134..].starts_with(b"TETRIS SET")
+}
@@ -1,87 +1,244 @@
-use std::fs::File;
-
+use bus::Bus;
 use cartridge::Cartridge;
+use error::BusFault;
 use gpu::Gpu;
+use joypad::Joypad;
+use mapper;
+use mapper::{Kind, Mapper};
+use serial::Serial;
+use timer::Timer;
+
+// Interrupt flags, in priority order
+pub const VBLANK_INTERRUPT: u8 = 0b0000_0001;
+pub const STAT_INTERRUPT:   u8 = 0b0000_0010;
+pub const TIMER_INTERRUPT:  u8 = 0b0000_0100;
+pub const SERIAL_INTERRUPT: u8 = 0b0000_1000;
+pub const JOYPAD_INTERRUPT: u8 = 0b0001_0000;
 
 pub struct Memory {
     pub gpu: Gpu,
-    pub rom: Vec<u8>,
+    pub timer: Timer,
+    pub joypad: Joypad,
+    pub serial: Serial,
+    pub mapper: Box<dyn Mapper>, // Cartridge ROM and RAM
     pub work_ram: [u8; 0x2000], // 8 kB of RAM
     pub high_ram: [u8; 0x7F], // from 0xFF80 to 0xFFFF
 
     pub interrupt_flags: u8,
     pub interrupt_enable: u8,
+
+    pub cgb: bool, // Running a Game Boy Color cartridge
+    pub double_speed: bool,
+    pub prepare_speed_switch: bool, // KEY1 bit 0, the switch happens on STOP
+
+    pub dma: u8, // DMA register, high byte of the OAM DMA source address
+    dma_cycles: u16, // Machine cycles left in the running OAM DMA transfer
+
+    // Report accesses to unused IO registers instead of reading 0xFF and ignoring
+    // writes like the hardware. Games do that all the time, so it's for debugging.
+    pub strict: bool,
+    pub fault: Option<BusFault>, // First unmapped access since the CPU last checked
 }
 
-// http://gbdev.gg8.se/wiki/articles/Memory_Map
 impl Memory {
-    pub fn new() -> Memory {
-        let mut file = File::open("roms/tetris.gb").unwrap();
-        let cartridge = Cartridge::load(&mut file);
+    // Unsupported cartridge types get a plain 32 kB ROM
+    pub fn new(cartridge: Cartridge) -> Memory {
+        let kind = Kind::detect(&cartridge).unwrap_or(Kind::RomOnly);
+        Memory::with_mapper(kind, cartridge)
+    }
+
+    pub fn with_mapper(kind: Kind, cartridge: Cartridge) -> Memory {
+        let cgb = cartridge.header.cgb_flag & 0x80 != 0;
 
         Memory {
             gpu: Gpu::new(),
-            rom: cartridge.rom,
+            timer: Timer::new(),
+            joypad: Joypad::new(),
+            serial: Serial::new(),
+            mapper: mapper::new(kind, cartridge),
             high_ram: [0; 0x7F],
             work_ram: [0; 0x2000],
             interrupt_flags: 0,
             interrupt_enable: 0,
+            cgb: cgb,
+            double_speed: false,
+            prepare_speed_switch: false,
+            dma: 0,
+            dma_cycles: 0,
+            strict: false,
+            fault: None,
+        }
+    }
+
+    // http://gbdev.gg8.se/wiki/articles/Video_Display#LCD_OAM_DMA_Transfers
+    // After a cycle of setup, one byte is copied to OAM every machine cycle.
+    fn start_dma(&mut self, value: u8) {
+        self.dma = value;
+        self.dma_cycles = 161;
+    }
+
+    fn step_dma(&mut self) {
+        if self.dma_cycles == 0 {
+            return;
+        }
+
+        self.dma_cycles -= 1;
+        if self.dma_cycles < 0xA0 {
+            let index = 0x9F - self.dma_cycles;
+            let address = (self.dma as u16) << 8 | index;
+            // Sources past the work RAM read from its echo
+            let address = if address >= 0xE000 { address - 0x2000 } else { address };
+            self.gpu.oam[index as usize] = self.read(address);
+        }
+    }
+
+    // While the transfer runs the CPU can only get to the IO registers and HRAM
+    fn dma_blocks(&self, address: u16) -> bool {
+        self.dma_cycles > 0 && self.dma_cycles <= 0xA0 && address < 0xFF00
+    }
+
+    // Nothing answers to the access: in strict mode remember it for the CPU, carry on either way
+    fn unmapped(&mut self, fault: BusFault) {
+        if self.strict && self.fault.is_none() {
+            self.fault = Some(fault);
+        }
+    }
+
+    pub fn read_io(&mut self, address: u16) -> u8 {
+        match address & 0xFF {
+            0x00 => self.joypad.load(),
+            0x01...0x02 => self.serial.load(address as u8),
+            0x04...0x07 => self.timer.load(address as u8), // Divider and timer
+            0x0F => self.interrupt_flags | 0xE0, // Interrupt flags, upper bits unused
+            0x10...0x3F => 0xFF, // TODO: Sound
+            0x46 => self.dma,
+            0x40...0x4B => self.gpu.load((address as u8) & 0xFF),
+            0x4D if self.cgb => {
+                // KEY1 - Prepare speed switch
+                ((self.double_speed as u8) << 7) | 0x7E | self.prepare_speed_switch as u8
+            }
+            _ => {
+                self.unmapped(BusFault::Read(address));
+                0xFF
+            }
+        }
+    }
+
+    // http://fms.komkon.org/GameBoy/Tech/Software.html
+    pub fn write_io(&mut self, address: u16, value: u8) {
+        match address & 0xFF {
+            0x00 => self.joypad.store(value),
+            0x01...0x02 => self.serial.store(address as u8, value),
+            0x04...0x07 => self.timer.store(address as u8, value), // Divider and timer
+            0x0F => { self.interrupt_flags = value & 0x1F } // Interrupt flags
+            0x10...0x3F => {} // TODO: Sound
+            0x46 => self.start_dma(value), // OAM DMA transfer
+            0x40...0x4B => self.gpu.store((address as u8) & 0xFF, value),
+            0x4D if self.cgb => { self.prepare_speed_switch = value & 0x01 != 0 }
+            _ => self.unmapped(BusFault::Write(address, value)),
         }
     }
 
-    pub fn load(&mut self, address: u16) -> u8 {
+    // http://gbdev.gg8.se/wiki/articles/Memory_Map
+    fn read(&mut self, address: u16) -> u8 {
         match address {
-            0x0000...0x7FFF => self.rom[address as usize],
+            0x0000...0x7FFF => self.mapper.load_rom(address),
             0x8000...0x9FFF => self.gpu.vram_load(address - 0x8000),
+            0xA000...0xBFFF => self.mapper.load_ram(address),
             0xC000...0xDFFF => self.work_ram[address as usize - 0xC000],
-            0xE000...0xFDFF => self.rom[address as usize - 0xE000],
+            0xE000...0xFDFF => self.work_ram[address as usize - 0xE000], // Echo of the work RAM
+            0xFE00...0xFE9F => self.gpu.oam[address as usize - 0xFE00],
+            0xFEA0...0xFEFF => 0, // Unusable
             0xFF00...0xFF7F => self.read_io(address),
             0xFF80...0xFFFE => self.high_ram[address as usize & 0x7F],
             0xFFFF => self.interrupt_enable,
-            _ => panic!("Can't read memory at {:04x}", address),
         }
     }
 
-    pub fn store(&mut self, address: u16, value: u8) {
+    fn write(&mut self, address: u16, value: u8) {
         match address {
-            0x0000...0x7FFF => { self.rom[address as usize] = value }
+            0x0000...0x7FFF => { self.mapper.store_rom(address, value) }
             0x8000...0x9FFF => { self.gpu.vram_store(address - 0x8000, value) },
+            0xA000...0xBFFF => { self.mapper.store_ram(address, value) },
             0xC000...0xDFFF => { self.work_ram[address as usize - 0xC000] = value },
+            0xE000...0xFDFF => { self.work_ram[address as usize - 0xE000] = value },
             0xFE00...0xFE9F => { self.gpu.oam[address as usize - 0xFE00] = value },
             0xFEA0...0xFEFF => { } // Unusable... weird
             0xFF00...0xFF7F => { self.write_io(address, value) },
             0xFF80...0xFFFE => { self.high_ram[address as usize & 0x7F] = value },
             0xFFFF => { self.interrupt_enable = value },
-            _ => panic!("Can't write memory at {:04x}", address),
         }
     }
+}
 
-    pub fn read_io(&mut self, address: u16) -> u8 {
-        match address & 0xFF {
-            0x00 => 0u8, // TODO: Joypad
-            // 0x01 | 0x02 => {} // Serial
-            // 0x04 => {} // Divider (???)
-            // 0x05...0x8 => {} // Timer
-            0x0F => self.interrupt_flags, // Interrupt flags
-            // 0x10...0x27 => {} // Sound
-            0x40...0x4B => self.gpu.load((address as u8) & 0xFF),
-            0x4C...0xFF => { 0 },
-            _    => { panic!("Can't read unknown IO register {:04X}", address) }
+impl Bus for Memory {
+    fn load(&mut self, address: u16) -> u8 {
+        if self.dma_blocks(address) {
+            return 0xFF;
         }
+        self.read(address)
     }
 
-    // http://fms.komkon.org/GameBoy/Tech/Software.html
-    pub fn write_io(&mut self, address: u16, value: u8) {
-        match address & 0xFF {
-            0x00 => {} // TODO: Joypad
-            0x01...0x02 => {} // TODO: Serial
-            // 0x04 => {} // Divider (???)
-            0x05...0x7 => {} // TODO: Timer
-            0x0F => { self.interrupt_flags = value } // Interrupt flags
-            0x10...0x26 => {} // TODO: Sound
-            0x40...0x4B => self.gpu.store((address as u8) & 0xFF, value),
-            0x4C...0xFF => {},
-            _ => { panic!("Can't write to unknown IO register {:04X}", address) }
+    fn store(&mut self, address: u16, value: u8) {
+        if !self.dma_blocks(address) {
+            self.write(address, value);
+        }
+    }
+
+    // Sees through a running DMA, and doesn't report unused IO registers
+    fn peek(&mut self, address: u16) -> u8 {
+        let fault = self.fault.take();
+        let value = self.read(address);
+        self.fault = fault;
+        value
+    }
+
+    fn tick(&mut self, cycles: u64) {
+        for _ in 0..cycles / 4 {
+            self.step_dma();
         }
+
+        // In double speed mode the LCD and the cartridge clock keep their pace while
+        // everything else runs twice as fast
+        let gpu_cycles = if self.double_speed { cycles / 2 } else { cycles };
+        self.gpu.step(gpu_cycles);
+        self.mapper.step(gpu_cycles);
+        self.timer.step(cycles);
+        self.serial.step(cycles);
+
+        self.interrupt_flags |= self.gpu.interrupts;
+        self.gpu.interrupts = 0;
+
+        if self.timer.interrupt {
+            self.interrupt_flags |= TIMER_INTERRUPT;
+            self.timer.interrupt = false;
+        }
+
+        if self.serial.interrupt {
+            self.interrupt_flags |= SERIAL_INTERRUPT;
+            self.serial.interrupt = false;
+        }
+
+        if self.joypad.interrupt {
+            self.interrupt_flags |= JOYPAD_INTERRUPT;
+            self.joypad.interrupt = false;
+        }
+    }
+
+    // Toggle between normal and double speed if it was requested through KEY1
+    fn stop(&mut self) -> bool {
+        self.timer.store(0x04, 0);
+
+        if !self.cgb || !self.prepare_speed_switch {
+            return false;
+        }
+
+        self.double_speed = !self.double_speed;
+        self.prepare_speed_switch = false;
+        true
+    }
+
+    fn take_fault(&mut self) -> Option<BusFault> {
+        self.fault.take()
     }
 }
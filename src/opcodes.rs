@@ -0,0 +1,568 @@
+// Everything known about each opcode besides what it does: the decoder, the
+// disassembler and the cycle accounting all read from here.
+// http://www.pastraiser.com/cpu/gameboy/gameboy_opcodes.html
+
+// Immediate data following the opcode, written in the mnemonic as d8, d16, a8, a16 or r8
+#[derive(Copy,Clone,PartialEq,Debug)]
+pub enum Operand {
+    None,
+    Byte, // d8
+    Word, // d16 or a16
+    HighPage, // a8, address in 0xFF00-0xFFFF
+    Relative, // r8, signed jump offset from the next instruction
+    Offset, // r8, signed offset added to SP
+}
+
+#[derive(Copy,Clone,PartialEq,Debug)]
+pub struct Opcode {
+    pub mnemonic: &'static str,
+    pub operand: Operand,
+    pub length: u8, // In bytes, opcode included
+    pub cycles: u8, // Clock cycles, or when a conditional branch is not taken
+    pub branch_cycles: u8, // Clock cycles when a conditional branch is taken
+}
+
+impl Opcode {
+    pub fn is_illegal(&self) -> bool {
+        self.mnemonic == ILLEGAL.mnemonic
+    }
+}
+
+macro_rules! op {
+    ($mnemonic:expr, $operand:ident, $length:expr, $cycles:expr) => {
+        op!($mnemonic, $operand, $length, $cycles, $cycles)
+    };
+    ($mnemonic:expr, $operand:ident, $length:expr, $cycles:expr, $branch_cycles:expr) => {
+        Opcode {
+            mnemonic: $mnemonic,
+            operand: Operand::$operand,
+            length: $length,
+            cycles: $cycles,
+            branch_cycles: $branch_cycles,
+        }
+    };
+}
+
+// Unused opcodes lock up the CPU
+const ILLEGAL: Opcode = op!("ILLEGAL", None, 1, 4);
+
+// STOP is followed by a byte that is skipped. The operand of the CB prefix is
+// the opcode to look up in CB_OPCODES, which has the cycles for both.
+pub static OPCODES: [Opcode; 256] = [
+    op!("NOP", None, 1, 4),                 // 0x00
+    op!("LD BC,d16", Word, 3, 12),          // 0x01
+    op!("LD (BC),A", None, 1, 8),           // 0x02
+    op!("INC BC", None, 1, 8),              // 0x03
+    op!("INC B", None, 1, 4),               // 0x04
+    op!("DEC B", None, 1, 4),               // 0x05
+    op!("LD B,d8", Byte, 2, 8),             // 0x06
+    op!("RLCA", None, 1, 4),                // 0x07
+    op!("LD (a16),SP", Word, 3, 20),        // 0x08
+    op!("ADD HL,BC", None, 1, 8),           // 0x09
+    op!("LD A,(BC)", None, 1, 8),           // 0x0A
+    op!("DEC BC", None, 1, 8),              // 0x0B
+    op!("INC C", None, 1, 4),               // 0x0C
+    op!("DEC C", None, 1, 4),               // 0x0D
+    op!("LD C,d8", Byte, 2, 8),             // 0x0E
+    op!("RRCA", None, 1, 4),                // 0x0F
+    op!("STOP", None, 2, 4),                // 0x10
+    op!("LD DE,d16", Word, 3, 12),          // 0x11
+    op!("LD (DE),A", None, 1, 8),           // 0x12
+    op!("INC DE", None, 1, 8),              // 0x13
+    op!("INC D", None, 1, 4),               // 0x14
+    op!("DEC D", None, 1, 4),               // 0x15
+    op!("LD D,d8", Byte, 2, 8),             // 0x16
+    op!("RLA", None, 1, 4),                 // 0x17
+    op!("JR r8", Relative, 2, 12),          // 0x18
+    op!("ADD HL,DE", None, 1, 8),           // 0x19
+    op!("LD A,(DE)", None, 1, 8),           // 0x1A
+    op!("DEC DE", None, 1, 8),              // 0x1B
+    op!("INC E", None, 1, 4),               // 0x1C
+    op!("DEC E", None, 1, 4),               // 0x1D
+    op!("LD E,d8", Byte, 2, 8),             // 0x1E
+    op!("RRA", None, 1, 4),                 // 0x1F
+    op!("JR NZ,r8", Relative, 2, 8, 12),    // 0x20
+    op!("LD HL,d16", Word, 3, 12),          // 0x21
+    op!("LD (HL+),A", None, 1, 8),          // 0x22
+    op!("INC HL", None, 1, 8),              // 0x23
+    op!("INC H", None, 1, 4),               // 0x24
+    op!("DEC H", None, 1, 4),               // 0x25
+    op!("LD H,d8", Byte, 2, 8),             // 0x26
+    op!("DAA", None, 1, 4),                 // 0x27
+    op!("JR Z,r8", Relative, 2, 8, 12),     // 0x28
+    op!("ADD HL,HL", None, 1, 8),           // 0x29
+    op!("LD A,(HL+)", None, 1, 8),          // 0x2A
+    op!("DEC HL", None, 1, 8),              // 0x2B
+    op!("INC L", None, 1, 4),               // 0x2C
+    op!("DEC L", None, 1, 4),               // 0x2D
+    op!("LD L,d8", Byte, 2, 8),             // 0x2E
+    op!("CPL", None, 1, 4),                 // 0x2F
+    op!("JR NC,r8", Relative, 2, 8, 12),    // 0x30
+    op!("LD SP,d16", Word, 3, 12),          // 0x31
+    op!("LD (HL-),A", None, 1, 8),          // 0x32
+    op!("INC SP", None, 1, 8),              // 0x33
+    op!("INC (HL)", None, 1, 12),           // 0x34
+    op!("DEC (HL)", None, 1, 12),           // 0x35
+    op!("LD (HL),d8", Byte, 2, 12),         // 0x36
+    op!("SCF", None, 1, 4),                 // 0x37
+    op!("JR C,r8", Relative, 2, 8, 12),     // 0x38
+    op!("ADD HL,SP", None, 1, 8),           // 0x39
+    op!("LD A,(HL-)", None, 1, 8),          // 0x3A
+    op!("DEC SP", None, 1, 8),              // 0x3B
+    op!("INC A", None, 1, 4),               // 0x3C
+    op!("DEC A", None, 1, 4),               // 0x3D
+    op!("LD A,d8", Byte, 2, 8),             // 0x3E
+    op!("CCF", None, 1, 4),                 // 0x3F
+    op!("LD B,B", None, 1, 4),              // 0x40
+    op!("LD B,C", None, 1, 4),              // 0x41
+    op!("LD B,D", None, 1, 4),              // 0x42
+    op!("LD B,E", None, 1, 4),              // 0x43
+    op!("LD B,H", None, 1, 4),              // 0x44
+    op!("LD B,L", None, 1, 4),              // 0x45
+    op!("LD B,(HL)", None, 1, 8),           // 0x46
+    op!("LD B,A", None, 1, 4),              // 0x47
+    op!("LD C,B", None, 1, 4),              // 0x48
+    op!("LD C,C", None, 1, 4),              // 0x49
+    op!("LD C,D", None, 1, 4),              // 0x4A
+    op!("LD C,E", None, 1, 4),              // 0x4B
+    op!("LD C,H", None, 1, 4),              // 0x4C
+    op!("LD C,L", None, 1, 4),              // 0x4D
+    op!("LD C,(HL)", None, 1, 8),           // 0x4E
+    op!("LD C,A", None, 1, 4),              // 0x4F
+    op!("LD D,B", None, 1, 4),              // 0x50
+    op!("LD D,C", None, 1, 4),              // 0x51
+    op!("LD D,D", None, 1, 4),              // 0x52
+    op!("LD D,E", None, 1, 4),              // 0x53
+    op!("LD D,H", None, 1, 4),              // 0x54
+    op!("LD D,L", None, 1, 4),              // 0x55
+    op!("LD D,(HL)", None, 1, 8),           // 0x56
+    op!("LD D,A", None, 1, 4),              // 0x57
+    op!("LD E,B", None, 1, 4),              // 0x58
+    op!("LD E,C", None, 1, 4),              // 0x59
+    op!("LD E,D", None, 1, 4),              // 0x5A
+    op!("LD E,E", None, 1, 4),              // 0x5B
+    op!("LD E,H", None, 1, 4),              // 0x5C
+    op!("LD E,L", None, 1, 4),              // 0x5D
+    op!("LD E,(HL)", None, 1, 8),           // 0x5E
+    op!("LD E,A", None, 1, 4),              // 0x5F
+    op!("LD H,B", None, 1, 4),              // 0x60
+    op!("LD H,C", None, 1, 4),              // 0x61
+    op!("LD H,D", None, 1, 4),              // 0x62
+    op!("LD H,E", None, 1, 4),              // 0x63
+    op!("LD H,H", None, 1, 4),              // 0x64
+    op!("LD H,L", None, 1, 4),              // 0x65
+    op!("LD H,(HL)", None, 1, 8),           // 0x66
+    op!("LD H,A", None, 1, 4),              // 0x67
+    op!("LD L,B", None, 1, 4),              // 0x68
+    op!("LD L,C", None, 1, 4),              // 0x69
+    op!("LD L,D", None, 1, 4),              // 0x6A
+    op!("LD L,E", None, 1, 4),              // 0x6B
+    op!("LD L,H", None, 1, 4),              // 0x6C
+    op!("LD L,L", None, 1, 4),              // 0x6D
+    op!("LD L,(HL)", None, 1, 8),           // 0x6E
+    op!("LD L,A", None, 1, 4),              // 0x6F
+    op!("LD (HL),B", None, 1, 8),           // 0x70
+    op!("LD (HL),C", None, 1, 8),           // 0x71
+    op!("LD (HL),D", None, 1, 8),           // 0x72
+    op!("LD (HL),E", None, 1, 8),           // 0x73
+    op!("LD (HL),H", None, 1, 8),           // 0x74
+    op!("LD (HL),L", None, 1, 8),           // 0x75
+    op!("HALT", None, 1, 4),                // 0x76
+    op!("LD (HL),A", None, 1, 8),           // 0x77
+    op!("LD A,B", None, 1, 4),              // 0x78
+    op!("LD A,C", None, 1, 4),              // 0x79
+    op!("LD A,D", None, 1, 4),              // 0x7A
+    op!("LD A,E", None, 1, 4),              // 0x7B
+    op!("LD A,H", None, 1, 4),              // 0x7C
+    op!("LD A,L", None, 1, 4),              // 0x7D
+    op!("LD A,(HL)", None, 1, 8),           // 0x7E
+    op!("LD A,A", None, 1, 4),              // 0x7F
+    op!("ADD A,B", None, 1, 4),             // 0x80
+    op!("ADD A,C", None, 1, 4),             // 0x81
+    op!("ADD A,D", None, 1, 4),             // 0x82
+    op!("ADD A,E", None, 1, 4),             // 0x83
+    op!("ADD A,H", None, 1, 4),             // 0x84
+    op!("ADD A,L", None, 1, 4),             // 0x85
+    op!("ADD A,(HL)", None, 1, 8),          // 0x86
+    op!("ADD A,A", None, 1, 4),             // 0x87
+    op!("ADC A,B", None, 1, 4),             // 0x88
+    op!("ADC A,C", None, 1, 4),             // 0x89
+    op!("ADC A,D", None, 1, 4),             // 0x8A
+    op!("ADC A,E", None, 1, 4),             // 0x8B
+    op!("ADC A,H", None, 1, 4),             // 0x8C
+    op!("ADC A,L", None, 1, 4),             // 0x8D
+    op!("ADC A,(HL)", None, 1, 8),          // 0x8E
+    op!("ADC A,A", None, 1, 4),             // 0x8F
+    op!("SUB B", None, 1, 4),               // 0x90
+    op!("SUB C", None, 1, 4),               // 0x91
+    op!("SUB D", None, 1, 4),               // 0x92
+    op!("SUB E", None, 1, 4),               // 0x93
+    op!("SUB H", None, 1, 4),               // 0x94
+    op!("SUB L", None, 1, 4),               // 0x95
+    op!("SUB (HL)", None, 1, 8),            // 0x96
+    op!("SUB A", None, 1, 4),               // 0x97
+    op!("SBC A,B", None, 1, 4),             // 0x98
+    op!("SBC A,C", None, 1, 4),             // 0x99
+    op!("SBC A,D", None, 1, 4),             // 0x9A
+    op!("SBC A,E", None, 1, 4),             // 0x9B
+    op!("SBC A,H", None, 1, 4),             // 0x9C
+    op!("SBC A,L", None, 1, 4),             // 0x9D
+    op!("SBC A,(HL)", None, 1, 8),          // 0x9E
+    op!("SBC A,A", None, 1, 4),             // 0x9F
+    op!("AND B", None, 1, 4),               // 0xA0
+    op!("AND C", None, 1, 4),               // 0xA1
+    op!("AND D", None, 1, 4),               // 0xA2
+    op!("AND E", None, 1, 4),               // 0xA3
+    op!("AND H", None, 1, 4),               // 0xA4
+    op!("AND L", None, 1, 4),               // 0xA5
+    op!("AND (HL)", None, 1, 8),            // 0xA6
+    op!("AND A", None, 1, 4),               // 0xA7
+    op!("XOR B", None, 1, 4),               // 0xA8
+    op!("XOR C", None, 1, 4),               // 0xA9
+    op!("XOR D", None, 1, 4),               // 0xAA
+    op!("XOR E", None, 1, 4),               // 0xAB
+    op!("XOR H", None, 1, 4),               // 0xAC
+    op!("XOR L", None, 1, 4),               // 0xAD
+    op!("XOR (HL)", None, 1, 8),            // 0xAE
+    op!("XOR A", None, 1, 4),               // 0xAF
+    op!("OR B", None, 1, 4),                // 0xB0
+    op!("OR C", None, 1, 4),                // 0xB1
+    op!("OR D", None, 1, 4),                // 0xB2
+    op!("OR E", None, 1, 4),                // 0xB3
+    op!("OR H", None, 1, 4),                // 0xB4
+    op!("OR L", None, 1, 4),                // 0xB5
+    op!("OR (HL)", None, 1, 8),             // 0xB6
+    op!("OR A", None, 1, 4),                // 0xB7
+    op!("CP B", None, 1, 4),                // 0xB8
+    op!("CP C", None, 1, 4),                // 0xB9
+    op!("CP D", None, 1, 4),                // 0xBA
+    op!("CP E", None, 1, 4),                // 0xBB
+    op!("CP H", None, 1, 4),                // 0xBC
+    op!("CP L", None, 1, 4),                // 0xBD
+    op!("CP (HL)", None, 1, 8),             // 0xBE
+    op!("CP A", None, 1, 4),                // 0xBF
+    op!("RET NZ", None, 1, 8, 20),          // 0xC0
+    op!("POP BC", None, 1, 12),             // 0xC1
+    op!("JP NZ,a16", Word, 3, 12, 16),      // 0xC2
+    op!("JP a16", Word, 3, 16),             // 0xC3
+    op!("CALL NZ,a16", Word, 3, 12, 24),    // 0xC4
+    op!("PUSH BC", None, 1, 16),            // 0xC5
+    op!("ADD A,d8", Byte, 2, 8),            // 0xC6
+    op!("RST $00", None, 1, 16),            // 0xC7
+    op!("RET Z", None, 1, 8, 20),           // 0xC8
+    op!("RET", None, 1, 16),                // 0xC9
+    op!("JP Z,a16", Word, 3, 12, 16),       // 0xCA
+    op!("PREFIX CB", Byte, 2, 0),           // 0xCB
+    op!("CALL Z,a16", Word, 3, 12, 24),     // 0xCC
+    op!("CALL a16", Word, 3, 24),           // 0xCD
+    op!("ADC A,d8", Byte, 2, 8),            // 0xCE
+    op!("RST $08", None, 1, 16),            // 0xCF
+    op!("RET NC", None, 1, 8, 20),          // 0xD0
+    op!("POP DE", None, 1, 12),             // 0xD1
+    op!("JP NC,a16", Word, 3, 12, 16),      // 0xD2
+    ILLEGAL,                                // 0xD3
+    op!("CALL NC,a16", Word, 3, 12, 24),    // 0xD4
+    op!("PUSH DE", None, 1, 16),            // 0xD5
+    op!("SUB d8", Byte, 2, 8),              // 0xD6
+    op!("RST $10", None, 1, 16),            // 0xD7
+    op!("RET C", None, 1, 8, 20),           // 0xD8
+    op!("RETI", None, 1, 16),               // 0xD9
+    op!("JP C,a16", Word, 3, 12, 16),       // 0xDA
+    ILLEGAL,                                // 0xDB
+    op!("CALL C,a16", Word, 3, 12, 24),     // 0xDC
+    ILLEGAL,                                // 0xDD
+    op!("SBC A,d8", Byte, 2, 8),            // 0xDE
+    op!("RST $18", None, 1, 16),            // 0xDF
+    op!("LDH (a8),A", HighPage, 2, 12),     // 0xE0
+    op!("POP HL", None, 1, 12),             // 0xE1
+    op!("LD (C),A", None, 1, 8),            // 0xE2
+    ILLEGAL,                                // 0xE3
+    ILLEGAL,                                // 0xE4
+    op!("PUSH HL", None, 1, 16),            // 0xE5
+    op!("AND d8", Byte, 2, 8),              // 0xE6
+    op!("RST $20", None, 1, 16),            // 0xE7
+    op!("ADD SP,r8", Offset, 2, 16),        // 0xE8
+    op!("JP HL", None, 1, 4),               // 0xE9
+    op!("LD (a16),A", Word, 3, 16),         // 0xEA
+    ILLEGAL,                                // 0xEB
+    ILLEGAL,                                // 0xEC
+    ILLEGAL,                                // 0xED
+    op!("XOR d8", Byte, 2, 8),              // 0xEE
+    op!("RST $28", None, 1, 16),            // 0xEF
+    op!("LDH A,(a8)", HighPage, 2, 12),     // 0xF0
+    op!("POP AF", None, 1, 12),             // 0xF1
+    op!("LD A,(C)", None, 1, 8),            // 0xF2
+    op!("DI", None, 1, 4),                  // 0xF3
+    ILLEGAL,                                // 0xF4
+    op!("PUSH AF", None, 1, 16),            // 0xF5
+    op!("OR d8", Byte, 2, 8),               // 0xF6
+    op!("RST $30", None, 1, 16),            // 0xF7
+    op!("LD HL,SP+r8", Offset, 2, 12),      // 0xF8
+    op!("LD SP,HL", None, 1, 8),            // 0xF9
+    op!("LD A,(a16)", Word, 3, 16),         // 0xFA
+    op!("EI", None, 1, 4),                  // 0xFB
+    ILLEGAL,                                // 0xFC
+    ILLEGAL,                                // 0xFD
+    op!("CP d8", Byte, 2, 8),               // 0xFE
+    op!("RST $38", None, 1, 16),            // 0xFF
+];
+
+// The CB prefix is included in the length and cycles
+pub static CB_OPCODES: [Opcode; 256] = [
+    op!("RLC B", None, 2, 8),               // 0x00
+    op!("RLC C", None, 2, 8),               // 0x01
+    op!("RLC D", None, 2, 8),               // 0x02
+    op!("RLC E", None, 2, 8),               // 0x03
+    op!("RLC H", None, 2, 8),               // 0x04
+    op!("RLC L", None, 2, 8),               // 0x05
+    op!("RLC (HL)", None, 2, 16),           // 0x06
+    op!("RLC A", None, 2, 8),               // 0x07
+    op!("RRC B", None, 2, 8),               // 0x08
+    op!("RRC C", None, 2, 8),               // 0x09
+    op!("RRC D", None, 2, 8),               // 0x0A
+    op!("RRC E", None, 2, 8),               // 0x0B
+    op!("RRC H", None, 2, 8),               // 0x0C
+    op!("RRC L", None, 2, 8),               // 0x0D
+    op!("RRC (HL)", None, 2, 16),           // 0x0E
+    op!("RRC A", None, 2, 8),               // 0x0F
+    op!("RL B", None, 2, 8),                // 0x10
+    op!("RL C", None, 2, 8),                // 0x11
+    op!("RL D", None, 2, 8),                // 0x12
+    op!("RL E", None, 2, 8),                // 0x13
+    op!("RL H", None, 2, 8),                // 0x14
+    op!("RL L", None, 2, 8),                // 0x15
+    op!("RL (HL)", None, 2, 16),            // 0x16
+    op!("RL A", None, 2, 8),                // 0x17
+    op!("RR B", None, 2, 8),                // 0x18
+    op!("RR C", None, 2, 8),                // 0x19
+    op!("RR D", None, 2, 8),                // 0x1A
+    op!("RR E", None, 2, 8),                // 0x1B
+    op!("RR H", None, 2, 8),                // 0x1C
+    op!("RR L", None, 2, 8),                // 0x1D
+    op!("RR (HL)", None, 2, 16),            // 0x1E
+    op!("RR A", None, 2, 8),                // 0x1F
+    op!("SLA B", None, 2, 8),               // 0x20
+    op!("SLA C", None, 2, 8),               // 0x21
+    op!("SLA D", None, 2, 8),               // 0x22
+    op!("SLA E", None, 2, 8),               // 0x23
+    op!("SLA H", None, 2, 8),               // 0x24
+    op!("SLA L", None, 2, 8),               // 0x25
+    op!("SLA (HL)", None, 2, 16),           // 0x26
+    op!("SLA A", None, 2, 8),               // 0x27
+    op!("SRA B", None, 2, 8),               // 0x28
+    op!("SRA C", None, 2, 8),               // 0x29
+    op!("SRA D", None, 2, 8),               // 0x2A
+    op!("SRA E", None, 2, 8),               // 0x2B
+    op!("SRA H", None, 2, 8),               // 0x2C
+    op!("SRA L", None, 2, 8),               // 0x2D
+    op!("SRA (HL)", None, 2, 16),           // 0x2E
+    op!("SRA A", None, 2, 8),               // 0x2F
+    op!("SWAP B", None, 2, 8),              // 0x30
+    op!("SWAP C", None, 2, 8),              // 0x31
+    op!("SWAP D", None, 2, 8),              // 0x32
+    op!("SWAP E", None, 2, 8),              // 0x33
+    op!("SWAP H", None, 2, 8),              // 0x34
+    op!("SWAP L", None, 2, 8),              // 0x35
+    op!("SWAP (HL)", None, 2, 16),          // 0x36
+    op!("SWAP A", None, 2, 8),              // 0x37
+    op!("SRL B", None, 2, 8),               // 0x38
+    op!("SRL C", None, 2, 8),               // 0x39
+    op!("SRL D", None, 2, 8),               // 0x3A
+    op!("SRL E", None, 2, 8),               // 0x3B
+    op!("SRL H", None, 2, 8),               // 0x3C
+    op!("SRL L", None, 2, 8),               // 0x3D
+    op!("SRL (HL)", None, 2, 16),           // 0x3E
+    op!("SRL A", None, 2, 8),               // 0x3F
+    op!("BIT 0,B", None, 2, 8),             // 0x40
+    op!("BIT 0,C", None, 2, 8),             // 0x41
+    op!("BIT 0,D", None, 2, 8),             // 0x42
+    op!("BIT 0,E", None, 2, 8),             // 0x43
+    op!("BIT 0,H", None, 2, 8),             // 0x44
+    op!("BIT 0,L", None, 2, 8),             // 0x45
+    op!("BIT 0,(HL)", None, 2, 12),         // 0x46
+    op!("BIT 0,A", None, 2, 8),             // 0x47
+    op!("BIT 1,B", None, 2, 8),             // 0x48
+    op!("BIT 1,C", None, 2, 8),             // 0x49
+    op!("BIT 1,D", None, 2, 8),             // 0x4A
+    op!("BIT 1,E", None, 2, 8),             // 0x4B
+    op!("BIT 1,H", None, 2, 8),             // 0x4C
+    op!("BIT 1,L", None, 2, 8),             // 0x4D
+    op!("BIT 1,(HL)", None, 2, 12),         // 0x4E
+    op!("BIT 1,A", None, 2, 8),             // 0x4F
+    op!("BIT 2,B", None, 2, 8),             // 0x50
+    op!("BIT 2,C", None, 2, 8),             // 0x51
+    op!("BIT 2,D", None, 2, 8),             // 0x52
+    op!("BIT 2,E", None, 2, 8),             // 0x53
+    op!("BIT 2,H", None, 2, 8),             // 0x54
+    op!("BIT 2,L", None, 2, 8),             // 0x55
+    op!("BIT 2,(HL)", None, 2, 12),         // 0x56
+    op!("BIT 2,A", None, 2, 8),             // 0x57
+    op!("BIT 3,B", None, 2, 8),             // 0x58
+    op!("BIT 3,C", None, 2, 8),             // 0x59
+    op!("BIT 3,D", None, 2, 8),             // 0x5A
+    op!("BIT 3,E", None, 2, 8),             // 0x5B
+    op!("BIT 3,H", None, 2, 8),             // 0x5C
+    op!("BIT 3,L", None, 2, 8),             // 0x5D
+    op!("BIT 3,(HL)", None, 2, 12),         // 0x5E
+    op!("BIT 3,A", None, 2, 8),             // 0x5F
+    op!("BIT 4,B", None, 2, 8),             // 0x60
+    op!("BIT 4,C", None, 2, 8),             // 0x61
+    op!("BIT 4,D", None, 2, 8),             // 0x62
+    op!("BIT 4,E", None, 2, 8),             // 0x63
+    op!("BIT 4,H", None, 2, 8),             // 0x64
+    op!("BIT 4,L", None, 2, 8),             // 0x65
+    op!("BIT 4,(HL)", None, 2, 12),         // 0x66
+    op!("BIT 4,A", None, 2, 8),             // 0x67
+    op!("BIT 5,B", None, 2, 8),             // 0x68
+    op!("BIT 5,C", None, 2, 8),             // 0x69
+    op!("BIT 5,D", None, 2, 8),             // 0x6A
+    op!("BIT 5,E", None, 2, 8),             // 0x6B
+    op!("BIT 5,H", None, 2, 8),             // 0x6C
+    op!("BIT 5,L", None, 2, 8),             // 0x6D
+    op!("BIT 5,(HL)", None, 2, 12),         // 0x6E
+    op!("BIT 5,A", None, 2, 8),             // 0x6F
+    op!("BIT 6,B", None, 2, 8),             // 0x70
+    op!("BIT 6,C", None, 2, 8),             // 0x71
+    op!("BIT 6,D", None, 2, 8),             // 0x72
+    op!("BIT 6,E", None, 2, 8),             // 0x73
+    op!("BIT 6,H", None, 2, 8),             // 0x74
+    op!("BIT 6,L", None, 2, 8),             // 0x75
+    op!("BIT 6,(HL)", None, 2, 12),         // 0x76
+    op!("BIT 6,A", None, 2, 8),             // 0x77
+    op!("BIT 7,B", None, 2, 8),             // 0x78
+    op!("BIT 7,C", None, 2, 8),             // 0x79
+    op!("BIT 7,D", None, 2, 8),             // 0x7A
+    op!("BIT 7,E", None, 2, 8),             // 0x7B
+    op!("BIT 7,H", None, 2, 8),             // 0x7C
+    op!("BIT 7,L", None, 2, 8),             // 0x7D
+    op!("BIT 7,(HL)", None, 2, 12),         // 0x7E
+    op!("BIT 7,A", None, 2, 8),             // 0x7F
+    op!("RES 0,B", None, 2, 8),             // 0x80
+    op!("RES 0,C", None, 2, 8),             // 0x81
+    op!("RES 0,D", None, 2, 8),             // 0x82
+    op!("RES 0,E", None, 2, 8),             // 0x83
+    op!("RES 0,H", None, 2, 8),             // 0x84
+    op!("RES 0,L", None, 2, 8),             // 0x85
+    op!("RES 0,(HL)", None, 2, 16),         // 0x86
+    op!("RES 0,A", None, 2, 8),             // 0x87
+    op!("RES 1,B", None, 2, 8),             // 0x88
+    op!("RES 1,C", None, 2, 8),             // 0x89
+    op!("RES 1,D", None, 2, 8),             // 0x8A
+    op!("RES 1,E", None, 2, 8),             // 0x8B
+    op!("RES 1,H", None, 2, 8),             // 0x8C
+    op!("RES 1,L", None, 2, 8),             // 0x8D
+    op!("RES 1,(HL)", None, 2, 16),         // 0x8E
+    op!("RES 1,A", None, 2, 8),             // 0x8F
+    op!("RES 2,B", None, 2, 8),             // 0x90
+    op!("RES 2,C", None, 2, 8),             // 0x91
+    op!("RES 2,D", None, 2, 8),             // 0x92
+    op!("RES 2,E", None, 2, 8),             // 0x93
+    op!("RES 2,H", None, 2, 8),             // 0x94
+    op!("RES 2,L", None, 2, 8),             // 0x95
+    op!("RES 2,(HL)", None, 2, 16),         // 0x96
+    op!("RES 2,A", None, 2, 8),             // 0x97
+    op!("RES 3,B", None, 2, 8),             // 0x98
+    op!("RES 3,C", None, 2, 8),             // 0x99
+    op!("RES 3,D", None, 2, 8),             // 0x9A
+    op!("RES 3,E", None, 2, 8),             // 0x9B
+    op!("RES 3,H", None, 2, 8),             // 0x9C
+    op!("RES 3,L", None, 2, 8),             // 0x9D
+    op!("RES 3,(HL)", None, 2, 16),         // 0x9E
+    op!("RES 3,A", None, 2, 8),             // 0x9F
+    op!("RES 4,B", None, 2, 8),             // 0xA0
+    op!("RES 4,C", None, 2, 8),             // 0xA1
+    op!("RES 4,D", None, 2, 8),             // 0xA2
+    op!("RES 4,E", None, 2, 8),             // 0xA3
+    op!("RES 4,H", None, 2, 8),             // 0xA4
+    op!("RES 4,L", None, 2, 8),             // 0xA5
+    op!("RES 4,(HL)", None, 2, 16),         // 0xA6
+    op!("RES 4,A", None, 2, 8),             // 0xA7
+    op!("RES 5,B", None, 2, 8),             // 0xA8
+    op!("RES 5,C", None, 2, 8),             // 0xA9
+    op!("RES 5,D", None, 2, 8),             // 0xAA
+    op!("RES 5,E", None, 2, 8),             // 0xAB
+    op!("RES 5,H", None, 2, 8),             // 0xAC
+    op!("RES 5,L", None, 2, 8),             // 0xAD
+    op!("RES 5,(HL)", None, 2, 16),         // 0xAE
+    op!("RES 5,A", None, 2, 8),             // 0xAF
+    op!("RES 6,B", None, 2, 8),             // 0xB0
+    op!("RES 6,C", None, 2, 8),             // 0xB1
+    op!("RES 6,D", None, 2, 8),             // 0xB2
+    op!("RES 6,E", None, 2, 8),             // 0xB3
+    op!("RES 6,H", None, 2, 8),             // 0xB4
+    op!("RES 6,L", None, 2, 8),             // 0xB5
+    op!("RES 6,(HL)", None, 2, 16),         // 0xB6
+    op!("RES 6,A", None, 2, 8),             // 0xB7
+    op!("RES 7,B", None, 2, 8),             // 0xB8
+    op!("RES 7,C", None, 2, 8),             // 0xB9
+    op!("RES 7,D", None, 2, 8),             // 0xBA
+    op!("RES 7,E", None, 2, 8),             // 0xBB
+    op!("RES 7,H", None, 2, 8),             // 0xBC
+    op!("RES 7,L", None, 2, 8),             // 0xBD
+    op!("RES 7,(HL)", None, 2, 16),         // 0xBE
+    op!("RES 7,A", None, 2, 8),             // 0xBF
+    op!("SET 0,B", None, 2, 8),             // 0xC0
+    op!("SET 0,C", None, 2, 8),             // 0xC1
+    op!("SET 0,D", None, 2, 8),             // 0xC2
+    op!("SET 0,E", None, 2, 8),             // 0xC3
+    op!("SET 0,H", None, 2, 8),             // 0xC4
+    op!("SET 0,L", None, 2, 8),             // 0xC5
+    op!("SET 0,(HL)", None, 2, 16),         // 0xC6
+    op!("SET 0,A", None, 2, 8),             // 0xC7
+    op!("SET 1,B", None, 2, 8),             // 0xC8
+    op!("SET 1,C", None, 2, 8),             // 0xC9
+    op!("SET 1,D", None, 2, 8),             // 0xCA
+    op!("SET 1,E", None, 2, 8),             // 0xCB
+    op!("SET 1,H", None, 2, 8),             // 0xCC
+    op!("SET 1,L", None, 2, 8),             // 0xCD
+    op!("SET 1,(HL)", None, 2, 16),         // 0xCE
+    op!("SET 1,A", None, 2, 8),             // 0xCF
+    op!("SET 2,B", None, 2, 8),             // 0xD0
+    op!("SET 2,C", None, 2, 8),             // 0xD1
+    op!("SET 2,D", None, 2, 8),             // 0xD2
+    op!("SET 2,E", None, 2, 8),             // 0xD3
+    op!("SET 2,H", None, 2, 8),             // 0xD4
+    op!("SET 2,L", None, 2, 8),             // 0xD5
+    op!("SET 2,(HL)", None, 2, 16),         // 0xD6
+    op!("SET 2,A", None, 2, 8),             // 0xD7
+    op!("SET 3,B", None, 2, 8),             // 0xD8
+    op!("SET 3,C", None, 2, 8),             // 0xD9
+    op!("SET 3,D", None, 2, 8),             // 0xDA
+    op!("SET 3,E", None, 2, 8),             // 0xDB
+    op!("SET 3,H", None, 2, 8),             // 0xDC
+    op!("SET 3,L", None, 2, 8),             // 0xDD
+    op!("SET 3,(HL)", None, 2, 16),         // 0xDE
+    op!("SET 3,A", None, 2, 8),             // 0xDF
+    op!("SET 4,B", None, 2, 8),             // 0xE0
+    op!("SET 4,C", None, 2, 8),             // 0xE1
+    op!("SET 4,D", None, 2, 8),             // 0xE2
+    op!("SET 4,E", None, 2, 8),             // 0xE3
+    op!("SET 4,H", None, 2, 8),             // 0xE4
+    op!("SET 4,L", None, 2, 8),             // 0xE5
+    op!("SET 4,(HL)", None, 2, 16),         // 0xE6
+    op!("SET 4,A", None, 2, 8),             // 0xE7
+    op!("SET 5,B", None, 2, 8),             // 0xE8
+    op!("SET 5,C", None, 2, 8),             // 0xE9
+    op!("SET 5,D", None, 2, 8),             // 0xEA
+    op!("SET 5,E", None, 2, 8),             // 0xEB
+    op!("SET 5,H", None, 2, 8),             // 0xEC
+    op!("SET 5,L", None, 2, 8),             // 0xED
+    op!("SET 5,(HL)", None, 2, 16),         // 0xEE
+    op!("SET 5,A", None, 2, 8),             // 0xEF
+    op!("SET 6,B", None, 2, 8),             // 0xF0
+    op!("SET 6,C", None, 2, 8),             // 0xF1
+    op!("SET 6,D", None, 2, 8),             // 0xF2
+    op!("SET 6,E", None, 2, 8),             // 0xF3
+    op!("SET 6,H", None, 2, 8),             // 0xF4
+    op!("SET 6,L", None, 2, 8),             // 0xF5
+    op!("SET 6,(HL)", None, 2, 16),         // 0xF6
+    op!("SET 6,A", None, 2, 8),             // 0xF7
+    op!("SET 7,B", None, 2, 8),             // 0xF8
+    op!("SET 7,C", None, 2, 8),             // 0xF9
+    op!("SET 7,D", None, 2, 8),             // 0xFA
+    op!("SET 7,E", None, 2, 8),             // 0xFB
+    op!("SET 7,H", None, 2, 8),             // 0xFC
+    op!("SET 7,L", None, 2, 8),             // 0xFD
+    op!("SET 7,(HL)", None, 2, 16),         // 0xFE
+    op!("SET 7,A", None, 2, 8),             // 0xFF
+];
@@ -69,6 +69,14 @@ impl Registers {
         self.set_flag_if(ZERO_FLAG, condition);
     }
 
+    pub fn set_subtract(&mut self, condition: bool) {
+        self.set_flag_if(SUB_FLAG, condition);
+    }
+
+    pub fn set_half_carry(&mut self, condition: bool) {
+        self.set_flag_if(HALF_CARRY_FLAG, condition);
+    }
+
     pub fn set_carry(&mut self, condition: bool) {
         self.set_flag_if(CARRY_FLAG, condition);
     }
@@ -113,7 +121,8 @@ impl Registers {
         let lo = value as u8;
 
         match register {
-            Register16::AF => { self.a = hi; self.f = lo },
+            // The lower nibble of F is hardwired to zero
+            Register16::AF => { self.a = hi; self.f = lo & 0xF0 },
             Register16::BC => { self.b = hi; self.c = lo },
             Register16::DE => { self.d = hi; self.e = lo },
             Register16::HL => { self.h = hi; self.l = lo },
@@ -0,0 +1,150 @@
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use mapper::Mapper;
+
+// Five seconds of emulated time
+const AUTOSAVE_CYCLES: u64 = 5 * 4_194_304;
+
+// Battery backed cartridge RAM, kept in a .sav file next to the ROM. MBC3 saves
+// have the clock appended, in the 48 bytes footer BGB and VBA-M use: the five
+// registers then the five latched ones, each as a little endian u32, then the
+// time of the save as a u64 UNIX timestamp. Older files have a u32 timestamp.
+// HuC3 saves get the 17 bytes SameBoy uses: the u64 timestamp, then minutes and
+// days as u16, then the alarm we don't have, left as zeros.
+const HUC3_FOOTER: usize = 17;
+pub struct SaveFile {
+    pub path: PathBuf,
+    pub wall_clock: bool, // Move the clock forward by the time spent closed
+    ram: Vec<u8>, // What's in the file, to only write changes
+    cycles: u64, // Since the last autosave
+}
+
+impl SaveFile {
+    pub fn new<P: AsRef<Path>>(path: P) -> SaveFile {
+        SaveFile {
+            path: path.as_ref().to_path_buf(),
+            wall_clock: true,
+            ram: Vec::new(),
+            cycles: 0,
+        }
+    }
+
+    // game.gb saves to game.sav
+    pub fn for_rom<P: AsRef<Path>>(rom: P) -> SaveFile {
+        SaveFile::new(rom.as_ref().with_extension("sav"))
+    }
+
+    // Fills the cartridge RAM from the file, if there's one
+    pub fn load(&mut self, mapper: &mut dyn Mapper) -> io::Result<()> {
+        self.ram = mapper.ram().to_vec();
+
+        let mut contents = Vec::new();
+        match File::open(&self.path) {
+            Ok(mut file) => { file.read_to_end(&mut contents)?; }
+            Err(ref error) if error.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(error) => return Err(error),
+        }
+
+        let size = mapper.ram().len().min(contents.len());
+        mapper.ram_mut()[..size].copy_from_slice(&contents[..size]);
+        self.ram = mapper.ram().to_vec();
+
+        let wall_clock = self.wall_clock;
+        if let Some(rtc) = mapper.rtc() {
+            let footer = &contents[size..];
+            if footer.len() == 44 || footer.len() == 48 {
+                let register = |i: usize| footer[i * 4];
+                for i in 0..5 {
+                    rtc.store(0x08 + i as u8, register(i));
+                }
+                for i in 0..5 {
+                    rtc.latched[i] = register(5 + i);
+                }
+
+                let timestamp = footer[40..].iter().rev().fold(0u64, |t, &byte| t << 8 | byte as u64);
+                if wall_clock {
+                    rtc.catch_up(UNIX_EPOCH + Duration::from_secs(timestamp));
+                }
+            }
+        }
+
+        if let Some(clock) = mapper.huc3_clock() {
+            let footer = &contents[size..];
+            if footer.len() == HUC3_FOOTER {
+                let number = |bytes: &[u8]| bytes.iter().rev().fold(0u64, |n, &byte| n << 8 | byte as u64);
+                clock.set(number(&footer[8..10]) as u16, number(&footer[10..12]) as u16);
+                if wall_clock {
+                    clock.catch_up(UNIX_EPOCH + Duration::from_secs(number(&footer[..8])));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn contents(mapper: &mut dyn Mapper) -> Vec<u8> {
+        let mut contents = mapper.ram().to_vec();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+
+        if let Some(rtc) = mapper.rtc() {
+            let registers = rtc.registers();
+            for &register in registers.iter().chain(rtc.latched.iter()) {
+                contents.extend_from_slice(&[register, 0, 0, 0]);
+            }
+
+            for i in 0..8 {
+                contents.push((now >> (i * 8)) as u8);
+            }
+        }
+
+        if let Some(clock) = mapper.huc3_clock() {
+            for i in 0..8 {
+                contents.push((now >> (i * 8)) as u8);
+            }
+            contents.extend_from_slice(&[clock.minutes as u8, (clock.minutes >> 8) as u8]);
+            contents.extend_from_slice(&[clock.days as u8, (clock.days >> 8) as u8]);
+            contents.extend_from_slice(&[0; 5]);
+        }
+
+        contents
+    }
+
+    // Writes the whole thing to a temporary file first, so a crash halfway
+    // doesn't leave a broken save
+    pub fn write(&mut self, mapper: &mut dyn Mapper) -> io::Result<()> {
+        let contents = SaveFile::contents(mapper);
+
+        let mut temporary = self.path.clone().into_os_string();
+        temporary.push(".tmp");
+        {
+            let mut file = File::create(&temporary)?;
+            file.write_all(&contents)?;
+            file.sync_all()?;
+        }
+        fs::rename(&temporary, &self.path)?;
+
+        self.ram = mapper.ram().to_vec();
+        self.cycles = 0;
+        Ok(())
+    }
+
+    // Call with the cycles run since the last call. Every few seconds, saves
+    // if the RAM changed.
+    pub fn autosave(&mut self, mapper: &mut dyn Mapper, cycles: u64) -> io::Result<()> {
+        self.cycles += cycles;
+        if self.cycles < AUTOSAVE_CYCLES {
+            return Ok(());
+        }
+
+        self.cycles = 0;
+        if mapper.ram() != self.ram.as_slice() {
+            self.write(mapper)?;
+        }
+        Ok(())
+    }
+}
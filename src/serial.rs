@@ -0,0 +1,62 @@
+// http://gbdev.gg8.se/wiki/articles/Serial_Data_Transfer_(Link_Cable)
+// There is never anything on the other end of the cable: bytes sent out are
+// kept in output (test ROMs print through it) and 0xFF is shifted in.
+pub struct Serial {
+    pub data: u8, // SB
+    pub control: u8, // SC, bit 7 is set while a transfer is running
+    cycles: u64, // Left in the running transfer
+
+    pub output: Vec<u8>,
+    pub interrupt: bool,
+}
+
+// 8 bits at 8192 Hz with the internal clock
+const TRANSFER_CYCLES: u64 = 8 * 512;
+
+impl Serial {
+    pub fn new() -> Serial {
+        Serial {
+            data: 0,
+            control: 0,
+            cycles: 0,
+            output: Vec::new(),
+            interrupt: false,
+        }
+    }
+
+    pub fn step(&mut self, cycles: u64) {
+        if self.cycles == 0 {
+            return;
+        }
+
+        self.cycles = self.cycles.saturating_sub(cycles);
+        if self.cycles == 0 {
+            self.data = 0xFF;
+            self.control &= 0x7F;
+            self.interrupt = true;
+        }
+    }
+
+    pub fn load(&self, address: u8) -> u8 {
+        match address {
+            0x01 => self.data,
+            0x02 => self.control | 0x7E,
+            _ => 0xFF
+        }
+    }
+
+    pub fn store(&mut self, address: u8, value: u8) {
+        match address {
+            0x01 => { self.data = value },
+            0x02 => {
+                self.control = value & 0x81;
+                // Only transfers on the internal clock ever complete
+                if value & 0x81 == 0x81 {
+                    self.output.push(self.data);
+                    self.cycles = TRANSFER_CYCLES;
+                }
+            }
+            _ => {}
+        }
+    }
+}
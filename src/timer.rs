@@ -0,0 +1,88 @@
+// http://gbdev.gg8.se/wiki/articles/Timer_and_Divider_Registers
+pub struct Timer {
+    // DIV is the upper byte of this counter, incremented every clock cycle
+    pub counter: u16,
+    pub tima: u8,
+    pub tma: u8,
+    pub tac: u8,
+
+    pub interrupt: bool,
+}
+
+impl Timer {
+    pub fn new() -> Timer {
+        Timer {
+            counter: 0,
+            tima: 0,
+            tma: 0,
+            tac: 0,
+            interrupt: false,
+        }
+    }
+
+    pub fn step(&mut self, cycles: u64) {
+        // One machine cycle at a time, the counter bits we care about are all above bit 1
+        for _ in 0..cycles / 4 {
+            let counter = self.counter.wrapping_add(4);
+            self.set_counter(counter);
+        }
+    }
+
+    // TIMA is incremented on the falling edge of the counter bit selected by TAC
+    fn set_counter(&mut self, counter: u16) {
+        let before = self.timer_bit();
+        self.counter = counter;
+        if before && !self.timer_bit() {
+            self.increment();
+        }
+    }
+
+    fn timer_bit(&self) -> bool {
+        let bit = match self.tac & 0x3 {
+            0 => 9, // 4096 Hz
+            1 => 3, // 262144 Hz
+            2 => 5, // 65536 Hz
+            _ => 7, // 16384 Hz
+        };
+
+        self.tac & 0x4 != 0 && self.counter & (1 << bit) != 0
+    }
+
+    fn increment(&mut self) {
+        let (value, overflow) = self.tima.overflowing_add(1);
+        if overflow {
+            self.tima = self.tma;
+            self.interrupt = true;
+        } else {
+            self.tima = value;
+        }
+    }
+
+    pub fn load(&self, address: u8) -> u8 {
+        match address {
+            0x04 => (self.counter >> 8) as u8,
+            0x05 => self.tima,
+            0x06 => self.tma,
+            0x07 => self.tac | 0xF8,
+            _ => 0xFF
+        }
+    }
+
+    pub fn store(&mut self, address: u8, value: u8) {
+        match address {
+            // Any write resets the whole counter, which can tick TIMA
+            0x04 => self.set_counter(0),
+            0x05 => { self.tima = value },
+            0x06 => { self.tma = value },
+            0x07 => {
+                // Disabling or switching frequency can also cause a falling edge
+                let before = self.timer_bit();
+                self.tac = value & 0x7;
+                if before && !self.timer_bit() {
+                    self.increment();
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use bus::Bus;
+use cpu::Cpu;
+
+// Logs the CPU state before each instruction, in the format used by gameboy-doctor:
+// https://github.com/robert/gameboy-doctor
+pub struct Tracer {
+    output: Box<dyn Write>,
+}
+
+impl Tracer {
+    pub fn new<W: Write + 'static>(output: W) -> Tracer {
+        Tracer { output: Box::new(output) }
+    }
+
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Tracer> {
+        let file = File::create(path)?;
+        Ok(Tracer::new(BufWriter::new(file)))
+    }
+
+    pub fn trace<B: Bus>(&mut self, cpu: &mut Cpu<B>) -> io::Result<()> {
+        // Peeking at the bus, reading through the CPU would take time and
+        // touching unused IO registers would report a fault
+        let pc = cpu.registers.pc;
+        let pcmem = [
+            cpu.bus.peek(pc),
+            cpu.bus.peek(pc.wrapping_add(1)),
+            cpu.bus.peek(pc.wrapping_add(2)),
+            cpu.bus.peek(pc.wrapping_add(3)),
+        ];
+
+        let r = &cpu.registers;
+        writeln!(self.output,
+                 "A:{:02X} F:{:02X} B:{:02X} C:{:02X} D:{:02X} E:{:02X} H:{:02X} L:{:02X} \
+                  SP:{:04X} PC:{:04X} PCMEM:{:02X},{:02X},{:02X},{:02X}",
+                 r.a, r.f, r.b, r.c, r.d, r.e, r.h, r.l, r.sp, pc,
+                 pcmem[0], pcmem[1], pcmem[2], pcmem[3])
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.output.flush()
+    }
+}
@@ -0,0 +1,122 @@
+extern crate yob;
+
+mod common;
+
+use std::fs::File;
+use std::path::PathBuf;
+
+use yob::cartridge::Cartridge;
+use yob::cpu::Cpu;
+use yob::memory::Memory;
+
+// Blargg's test ROMs print their results through the serial port and end with
+// "Passed" or "Failed". They aren't distributed with yob, tests are skipped
+// unless they are found in roms/blargg: https://github.com/retrio/gb-test-roms
+
+const CLOCK_SPEED: u64 = 4_194_304;
+
+// Emulated time after which a ROM is considered stuck (the whole cpu_instrs takes about a minute)
+const TIMEOUT_CYCLES: u64 = CLOCK_SPEED * 120;
+
+// Runs the ROM until it reports, returns what it printed
+fn run(cartridge: Cartridge) -> Result<String, String> {
+    let mut cpu = Cpu::new(Memory::new(cartridge));
+    cpu.reset();
+
+    // Once the result is in, give it some time to print the details
+    let mut deadline = TIMEOUT_CYCLES;
+    let mut passed = None;
+    let mut printed = 0;
+
+    while cpu.cycles < deadline {
+        if let Err(error) = cpu.step() {
+            let output = String::from_utf8_lossy(&cpu.bus.serial.output);
+            return Err(format!("{}\n{}", output, error));
+        }
+
+        let output = &cpu.bus.serial.output;
+        if passed.is_none() && output.len() != printed {
+            printed = output.len();
+            let text = String::from_utf8_lossy(output);
+            if text.contains("Passed") || text.contains("Failed") {
+                passed = Some(text.contains("Passed"));
+                deadline = cpu.cycles + CLOCK_SPEED / 2;
+            }
+        }
+    }
+
+    let output = String::from_utf8_lossy(&cpu.bus.serial.output).into_owned();
+    match passed {
+        Some(true) => Ok(output),
+        Some(false) => Err(output),
+        None => Err(format!("{}\nTimed out", output)),
+    }
+}
+
+fn blargg(name: &str) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("roms/blargg").join(name);
+    let mut file = match File::open(&path) {
+        Ok(file) => file,
+        Err(_) => {
+            println!("Skipping {}, ROM not found", path.display());
+            return;
+        }
+    };
+
+    let cartridge = Cartridge::load(&mut file).unwrap();
+    if let Err(output) = run(cartridge) {
+        panic!("{} failed:\n{}", name, output);
+    }
+}
+
+#[test]
+fn cpu_instrs() {
+    blargg("cpu_instrs.gb");
+}
+
+#[test]
+fn instr_timing() {
+    blargg("instr_timing.gb");
+}
+
+#[test]
+fn mem_timing() {
+    blargg("mem_timing.gb");
+}
+
+#[test]
+fn halt_bug() {
+    blargg("halt_bug.gb");
+}
+
+// Prints a message through the serial port the way the test ROMs do, then hangs
+fn serial_rom(message: &str) -> Cartridge {
+    let mut rom = common::rom(0x00, 0x00);
+
+    let entry: &[u8] = &[
+        0xC3, 0x50, 0x01, // JP $0150
+    ];
+    rom[0x100..0x100 + entry.len()].copy_from_slice(entry);
+
+    let main: &[u8] = &[
+        0x21, 0x00, 0x02, // LD HL,$0200
+        0x2A,             // print: LD A,(HL+)
+        0xB7,             // OR A
+        0x28, 0x08,       // JR Z,done
+        0xE0, 0x01,       // LDH ($01),A
+        0x3E, 0x81,       // LD A,$81
+        0xE0, 0x02,       // LDH ($02),A
+        0x18, 0xF4,       // JR print
+        0x18, 0xFE,       // done: JR done
+    ];
+    rom[0x150..0x150 + main.len()].copy_from_slice(main);
+    rom[0x200..0x200 + message.len()].copy_from_slice(message.as_bytes());
+
+    common::cartridge(rom)
+}
+
+#[test]
+fn serial_capture() {
+    assert_eq!(run(serial_rom("cpu_instrs\n\nPassed\n")), Ok("cpu_instrs\n\nPassed\n".to_string()));
+    assert_eq!(run(serial_rom("01-special\n\nFailed #6\n")), Err("01-special\n\nFailed #6\n".to_string()));
+}
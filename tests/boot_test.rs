@@ -0,0 +1,107 @@
+extern crate yob;
+
+mod common;
+
+use std::fs::File;
+use std::path::PathBuf;
+
+use yob::bus::Bus;
+use yob::cartridge::Cartridge;
+use yob::cpu::Cpu;
+use yob::memory::Memory;
+
+// A tiny ROM booting the way Tetris does: it polls LY until line 0x94 to turn
+// the LCD off, draws its "title screen" into the tile map, then waits for
+// VBlank interrupts forever. Nothing but a working scanline counter gets it there.
+fn title_screen_rom() -> Cartridge {
+    let mut rom = common::rom(0x00, 0x00);
+
+    let vblank: &[u8] = &[
+        0xF0, 0x80,       // LDH A,($80)
+        0x3C,             // INC A
+        0xE0, 0x80,       // LDH ($80),A
+        0xD9,             // RETI
+    ];
+    rom[0x40..0x40 + vblank.len()].copy_from_slice(vblank);
+
+    let entry: &[u8] = &[
+        0x00,             // NOP
+        0xC3, 0x50, 0x01, // JP $0150
+    ];
+    rom[0x100..0x100 + entry.len()].copy_from_slice(entry);
+
+    let main: &[u8] = &[
+        0xF3,             // DI
+        0x31, 0xFE, 0xFF, // LD SP,$FFFE
+        0xF0, 0x44,       // wait: LDH A,($44)
+        0xFE, 0x94,       // CP $94
+        0x20, 0xFA,       // JR NZ,wait
+        0x3E, 0x03,       // LD A,$03
+        0xE0, 0x40,       // LDH ($40),A ; LCD off
+        0x21, 0x00, 0x98, // LD HL,$9800
+        0x06, 0x20,       // LD B,$20
+        0x3E, 0x01,       // fill: LD A,$01
+        0x22,             // LD (HL+),A
+        0x05,             // DEC B
+        0x20, 0xFA,       // JR NZ,fill
+        0x3E, 0x91,       // LD A,$91
+        0xE0, 0x40,       // LDH ($40),A ; LCD on
+        0x3E, 0x01,       // LD A,$01
+        0xE0, 0xFF,       // LDH ($FF),A ; VBlank only
+        0xFB,             // EI
+        0x76,             // title: HALT
+        0x18, 0xFD,       // JR title
+    ];
+    rom[0x150..0x150 + main.len()].copy_from_slice(main);
+
+    common::cartridge(rom)
+}
+
+#[test]
+fn boot_to_title_screen() {
+    let mut cpu = Cpu::new(Memory::new(title_screen_rom()));
+    cpu.reset();
+
+    // Ten seconds of emulated time is way more than needed
+    while cpu.cycles < 10 * 4_194_304 && cpu.bus.load(0xFF80) < 60 {
+        cpu.step().unwrap();
+    }
+
+    assert_eq!(cpu.bus.load(0xFF80), 60);
+    assert_eq!(cpu.bus.load(0x9800), 0x01);
+    assert_eq!(cpu.bus.load(0x981F), 0x01);
+}
+
+// The real thing, when roms/tetris.gb is there. It isn't distributed with yob
+// either so the test is skipped otherwise.
+const TETRIS_GAME_STATE: u16 = 0xFFE1;
+const TETRIS_TITLE_SCREEN: u8 = 0x07;
+
+#[test]
+fn boot_tetris() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("roms/tetris.gb");
+    let mut file = match File::open(&path) {
+        Ok(file) => file,
+        Err(_) => {
+            println!("Skipping {}, ROM not found", path.display());
+            return;
+        }
+    };
+
+    let cartridge = Cartridge::load(&mut file).unwrap();
+    let mut cpu = Cpu::new(Memory::new(cartridge));
+    cpu.reset();
+
+    // The copyright screen stays up for a few seconds
+    let mut frames = 0;
+    while frames < 600 && cpu.bus.load(TETRIS_GAME_STATE) != TETRIS_TITLE_SCREEN {
+        cpu.step().unwrap();
+        if cpu.bus.gpu.new_frame {
+            cpu.bus.gpu.new_frame = false;
+            frames += 1;
+        }
+    }
+
+    assert_eq!(cpu.bus.load(TETRIS_GAME_STATE), TETRIS_TITLE_SCREEN,
+               "no title screen after {} frames", frames);
+}
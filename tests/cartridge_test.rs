@@ -0,0 +1,101 @@
+extern crate yob;
+
+use yob::cartridge::{Cartridge, Header};
+use yob::error::CartridgeError;
+
+// A 64 kB MBC1 cartridge with RAM and a valid header
+fn rom() -> Vec<u8> {
+    let mut rom = vec![0; 0x10000];
+    rom[0x134..0x134 + 9].copy_from_slice(b"TEST GAME");
+    rom[0x144..0x146].copy_from_slice(b"01");
+    rom[0x146] = 0x03;
+    rom[0x147] = 0x03;
+    rom[0x148] = 0x01;
+    rom[0x149] = 0x02;
+    rom[0x14A] = 0x01;
+    rom[0x14B] = 0x33;
+    rom[0x14C] = 0x02;
+    rom[0x14D] = Header::checksum(&rom);
+    rom
+}
+
+#[test]
+fn header() {
+    let cartridge = Cartridge::new(rom()).unwrap();
+    let header = cartridge.header;
+    assert_eq!(header.title, "TEST GAME");
+    assert_eq!(header.manufacturer, "");
+    assert_eq!(header.cgb_flag, 0x00);
+    assert_eq!(header.new_licensee, "01");
+    assert_eq!(header.sgb_flag, 0x03);
+    assert_eq!(header.cartridge_type, 0x03);
+    assert_eq!(header.rom_size(), Some(0x10000));
+    assert_eq!(header.ram_size(), Some(0x2000));
+    assert_eq!(header.destination, 0x01);
+    assert_eq!(header.old_licensee, 0x33);
+    assert_eq!(header.version, 0x02);
+}
+
+#[test]
+fn cgb_header() {
+    let mut rom = rom();
+    rom[0x13F..0x144].copy_from_slice(b"ABCD\xC0");
+    rom[0x14D] = Header::checksum(&rom);
+    let header = Header::parse(&rom).unwrap();
+    assert_eq!(header.title, "TEST GAME");
+    assert_eq!(header.manufacturer, "ABCD");
+    assert_eq!(header.cgb_flag, 0xC0);
+}
+
+#[test]
+fn global_checksum() {
+    let mut rom = rom();
+    let sum = rom.iter().fold(0u16, |sum, &byte| sum.wrapping_add(byte as u16));
+    rom[0x14E] = (sum >> 8) as u8;
+    rom[0x14F] = sum as u8;
+    let header = Header::parse(&rom).unwrap();
+    assert!(header.global_checksum_matches(&rom));
+
+    rom[0x8000] = 1;
+    assert!(!header.global_checksum_matches(&rom));
+}
+
+#[test]
+fn truncated() {
+    match Cartridge::new(vec![0; 0x100]) {
+        Err(CartridgeError::Truncated { expected: 0x150, actual: 0x100 }) => {}
+        result => panic!("{:?}", result.err()),
+    }
+
+    let mut rom = rom();
+    rom.truncate(0x8000);
+    match Cartridge::new(rom) {
+        Err(CartridgeError::Truncated { expected: 0x10000, actual: 0x8000 }) => {}
+        result => panic!("{:?}", result.err()),
+    }
+}
+
+#[test]
+fn inconsistent() {
+    let mut rom = rom();
+    rom[0x134] = b'B';
+    match Cartridge::new(rom.clone()) {
+        Err(CartridgeError::HeaderChecksum { .. }) => {}
+        result => panic!("{:?}", result.err()),
+    }
+
+    rom[0x148] = 0x20;
+    rom[0x14D] = Header::checksum(&rom);
+    match Cartridge::new(rom.clone()) {
+        Err(CartridgeError::UnknownRomSize(0x20)) => {}
+        result => panic!("{:?}", result.err()),
+    }
+
+    rom[0x148] = 0x01;
+    rom[0x149] = 0x09;
+    rom[0x14D] = Header::checksum(&rom);
+    match Cartridge::new(rom) {
+        Err(CartridgeError::UnknownRamSize(0x09)) => {}
+        result => panic!("{:?}", result.err()),
+    }
+}
@@ -0,0 +1,24 @@
+// Shared by the integration tests through `mod common;`, not all of them use everything
+#![allow(dead_code)]
+
+use yob::cartridge::{Cartridge, Header};
+use yob::memory::Memory;
+
+// A blank ROM of the given cartridge type, 32 kB << rom_size_code long
+pub fn rom(cartridge_type: u8, rom_size_code: u8) -> Vec<u8> {
+    let mut rom = vec![0; 0x8000 << rom_size_code];
+    rom[0x147] = cartridge_type;
+    rom[0x148] = rom_size_code;
+    rom
+}
+
+// Fixes the header checksum up so the cartridge passes the checks
+pub fn cartridge(mut rom: Vec<u8>) -> Cartridge {
+    rom[0x14D] = Header::checksum(&rom);
+    Cartridge::new(rom).unwrap()
+}
+
+// The whole machine around an empty 32 kB cartridge
+pub fn memory() -> Memory {
+    Memory::new(cartridge(rom(0x00, 0x00)))
+}
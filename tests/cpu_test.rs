@@ -1,18 +1,33 @@
 extern crate yob;
 
-use yob::cpu::Cpu;
+mod common;
+
+use yob::bus::{Bus, FlatBus};
+use yob::cpu::{Cpu, IllegalOpcodePolicy, StepInfo};
+use yob::error::{BusFault, EmuError};
+use yob::joypad::Button;
+use yob::memory::Memory;
+use yob::opcodes::{Operand, OPCODES, CB_OPCODES};
 use yob::registers::*;
 
 // TODO: Test JP/JR/CALL/RET conditionals better
 // TODO: Test more flag management (especially ZERO and CARRY)
 
 #[cfg(test)]
-fn reset() -> Cpu { Cpu::new() }
+fn reset() -> Cpu<FlatBus> { Cpu::new(FlatBus::new()) }
 
-fn step(cpu: &mut Cpu, instr: u8, steps: i32) {
+// For the tests that need the rest of the hardware, running from work RAM
+#[cfg(test)]
+fn gameboy() -> Cpu<Memory> {
+    let mut cpu = Cpu::new(common::memory());
+    cpu.registers.pc = 0xC000;
+    cpu
+}
+
+fn step<B: Bus>(cpu: &mut Cpu<B>, instr: u8, steps: i32) {
     let pc = cpu.registers.pc;
-    cpu.store_byte(pc, instr);
-    cpu.step();
+    cpu.bus.store(pc, instr);
+    cpu.step().unwrap();
     // let steps_taken = cpu.registers.pc as i32 - pc as i32;
     // assert_eq!(steps_taken, steps);
 }
@@ -28,6 +43,10 @@ fn flags_ops() {
 
     step(&mut cpu, 0x3F, 1); // CCF
     assert!(!cpu.registers.test_flag(CARRY_FLAG));
+
+    cpu.registers.f = ZERO_FLAG | SUB_FLAG | HALF_CARRY_FLAG;
+    step(&mut cpu, 0x37, 1); // SCF
+    assert_eq!(cpu.registers.f, ZERO_FLAG | CARRY_FLAG);
 }
 
 #[test]
@@ -41,7 +60,7 @@ fn inc_a() {
     cpu.registers.a = 0xFF;
     step(&mut cpu, 0x3C, 1);
     assert_eq!(cpu.registers.a, 0);
-    assert_eq!(cpu.registers.f, ZERO_FLAG);
+    assert_eq!(cpu.registers.f, ZERO_FLAG | HALF_CARRY_FLAG);
 }
 
 #[test]
@@ -55,7 +74,14 @@ fn dec_a() {
     cpu.registers.a = 0x1;
     step(&mut cpu, 0x3D, 1);
     assert_eq!(cpu.registers.a, 0x0);
-    assert_eq!(cpu.registers.f, ZERO_FLAG);
+    assert_eq!(cpu.registers.f, ZERO_FLAG | SUB_FLAG);
+
+    let mut cpu = reset();
+    cpu.registers.a = 0x10;
+    cpu.registers.set_carry(true);
+    step(&mut cpu, 0x3D, 1);
+    assert_eq!(cpu.registers.a, 0x0F);
+    assert_eq!(cpu.registers.f, SUB_FLAG | HALF_CARRY_FLAG | CARRY_FLAG);
 }
 
 #[test]
@@ -78,18 +104,19 @@ fn dec_bc() {
     assert_eq!(cpu.registers.c, 0xFF);
     assert!(!cpu.registers.test_flag(ZERO_FLAG));
 
+    // 16 bits DEC doesn't touch the flags
     let mut cpu = reset();
     cpu.registers.b = 0;
     cpu.registers.c = 1;
     step(&mut cpu, 0x0B, 1);
-    assert_eq!(cpu.registers.hl(), 0);
-    assert!(cpu.registers.test_flag(ZERO_FLAG));
+    assert_eq!(cpu.registers.bc(), 0);
+    assert!(!cpu.registers.test_flag(ZERO_FLAG));
 }
 
 #[test]
 fn ld_b_immediate() {
     let mut cpu = reset();
-    cpu.store_byte(0x1, 0x42);
+    cpu.bus.store(0x1, 0x42);
     step(&mut cpu, 0x06, 2);
     assert_eq!(cpu.registers.b, 0x42);
 }
@@ -106,7 +133,7 @@ fn ld_bc_immediate() {
 fn ld_a_hli() {
     let mut cpu = reset();
     cpu.registers.store_16(Register16::HL, 0x1234);
-    cpu.store_byte(0x1234, 0x42);
+    cpu.bus.store(0x1234, 0x42);
     step(&mut cpu, 0x2A, 1);
     assert_eq!(cpu.registers.a, 0x42);
     assert_eq!(cpu.registers.hl(), 0x1235);
@@ -118,7 +145,7 @@ fn ld_hld_a() {
     cpu.registers.a = 0x42;
     cpu.registers.store_16(Register16::HL, 0x1234);
     step(&mut cpu, 0x32, 1);
-    let byte = cpu.load_byte(0x1234);
+    let byte = cpu.bus.load(0x1234);
     assert_eq!(byte, 0x42);
     assert_eq!(cpu.registers.hl(), 0x1233);
 }
@@ -129,7 +156,7 @@ fn ld_indirect_immediate_a() {
     cpu.registers.a = 0x42;
     cpu.store_word(0x1, 0x1234);
     step(&mut cpu, 0xEA, 3);
-    let byte = cpu.load_byte(0x1234);
+    let byte = cpu.bus.load(0x1234);
     assert_eq!(byte, 0x42);
 }
 
@@ -165,7 +192,7 @@ fn ld_b_hl() {
     let mut cpu = reset();
     cpu.registers.h = 0x15;
     cpu.registers.l = 0x20;
-    cpu.store_byte((0x15 << 8) | 0x20 , 0x42);
+    cpu.bus.store((0x15 << 8) | 0x20 , 0x42);
     step(&mut cpu, 0x46, 1);
     assert_eq!(cpu.registers.b, 0x42);
 }
@@ -177,15 +204,15 @@ fn ld_bc_a() {
     cpu.registers.b = 0x15;
     cpu.registers.c = 0x20;
     step(&mut cpu, 0x02, 1);
-    let byte = cpu.load_byte((0x15 << 8) | 0x20);
+    let byte = cpu.bus.load((0x15 << 8) | 0x20);
     assert_eq!(byte, 0x42);
 }
 
 // #[test]
 // fn ld_a_zero_page() {
 //     let mut cpu = reset();
-//     cpu.store_byte(0x1, 0x5);
-//     cpu.store_byte(0xFF05, 0x42);
+//     cpu.bus.store(0x1, 0x5);
+//     cpu.bus.store(0xFF05, 0x42);
 //     step(&mut cpu, 0xF0, 2);
 //     assert_eq!(cpu.registers.a, 0x42);
 // }
@@ -194,7 +221,7 @@ fn ld_bc_a() {
 // fn ld_a_zero_page_reg_c() {
 //     let mut cpu = reset();
 //     cpu.registers.c = 0x3;
-//     cpu.store_byte(0xFF03, 0x42);
+//     cpu.bus.store(0xFF03, 0x42);
 //     step(&mut cpu, 0xF2, 1);
 //     assert_eq!(cpu.registers.a, 0x42);
 // }
@@ -203,9 +230,9 @@ fn ld_bc_a() {
 // fn ld_zero_page_to_a() {
 //     let mut cpu = reset();
 //     cpu.registers.a = 0x42;
-//     cpu.store_byte(0x1, 0x3);
+//     cpu.bus.store(0x1, 0x3);
 //     step(&mut cpu, 0xE0, 2);
-//     let byte = cpu.load_byte(0xFF03);
+//     let byte = cpu.bus.load(0xFF03);
 //     assert_eq!(byte, 0x42);
 // }
 
@@ -215,7 +242,7 @@ fn ld_bc_a() {
 //     cpu.registers.a = 0x42;
 //     cpu.registers.c = 0x3;
 //     step(&mut cpu, 0xE2, 1);
-//     let byte = cpu.load_byte(0xFF03);
+//     let byte = cpu.bus.load(0xFF03);
 //     assert_eq!(byte, 0x42);
 // }
 
@@ -231,7 +258,7 @@ fn ld_sp_hl() {
 fn ld_hl_sp() {
     let mut cpu = reset();
     cpu.registers.sp = 0xFFFA;
-    cpu.store_byte(0x1, 0x2);
+    cpu.bus.store(0x1, 0x2);
     step(&mut cpu, 0xF8, 2);
     assert_eq!(cpu.registers.hl(), 0xFFFC);
 }
@@ -254,6 +281,23 @@ fn add_b() {
     assert_eq!(cpu.registers.carry(), 1);
 }
 
+#[test]
+fn add_half_carry() {
+    let mut cpu = reset();
+    cpu.registers.a = 0x0F;
+    cpu.registers.b = 0x01;
+    step(&mut cpu, 0x80, 1);
+    assert_eq!(cpu.registers.a, 0x10);
+    assert_eq!(cpu.registers.f, HALF_CARRY_FLAG);
+
+    let mut cpu = reset();
+    cpu.registers.a = 0xF0;
+    cpu.registers.b = 0x10;
+    step(&mut cpu, 0x80, 1);
+    assert_eq!(cpu.registers.a, 0x00);
+    assert_eq!(cpu.registers.f, ZERO_FLAG | CARRY_FLAG);
+}
+
 #[test]
 fn add_hl_bc() {
     let mut cpu = reset();
@@ -262,6 +306,14 @@ fn add_hl_bc() {
     step(&mut cpu, 0x09, 1);
     assert_eq!(cpu.registers.hl(), 0x20);
     assert_eq!(cpu.registers.carry(), 0);
+
+    let mut cpu = reset();
+    cpu.registers.set_zero(true);
+    cpu.registers.store_16(Register16::HL, 0x8FFF);
+    cpu.registers.store_16(Register16::BC, 0x8001);
+    step(&mut cpu, 0x09, 1);
+    assert_eq!(cpu.registers.hl(), 0x1000);
+    assert_eq!(cpu.registers.f, ZERO_FLAG | HALF_CARRY_FLAG | CARRY_FLAG);
 }
 
 #[test]
@@ -286,9 +338,16 @@ fn adc_b() {
 fn add_sp() {
     let mut cpu = reset();
     cpu.registers.sp = 0x02;
-    cpu.store_byte(0x1, 0x3);
+    cpu.bus.store(0x1, 0x3);
     step(&mut cpu, 0xE8, 2);
     assert_eq!(cpu.registers.sp, 0x5);
+
+    let mut cpu = reset();
+    cpu.registers.sp = 0x00FF;
+    cpu.bus.store(0x1, -1i8 as u8);
+    step(&mut cpu, 0xE8, 2);
+    assert_eq!(cpu.registers.sp, 0xFE);
+    assert_eq!(cpu.registers.f, HALF_CARRY_FLAG | CARRY_FLAG);
 }
 
 #[test]
@@ -306,7 +365,7 @@ fn sub_b() {
     cpu.registers.b = 0x02;
     step(&mut cpu, 0x90, 1);
     assert_eq!(cpu.registers.a, 0xFF);
-    assert_eq!(cpu.registers.carry(), 1);
+    assert_eq!(cpu.registers.f, SUB_FLAG | HALF_CARRY_FLAG | CARRY_FLAG);
 }
 
 #[test]
@@ -325,6 +384,14 @@ fn sbc_b() {
     step(&mut cpu, 0x98, 1);
     assert_eq!(cpu.registers.a, 0xFF);
     assert_eq!(cpu.registers.carry(), 1);
+
+    let mut cpu = reset();
+    cpu.registers.a = 0x00;
+    cpu.registers.b = 0xFF;
+    cpu.registers.set_carry(true);
+    step(&mut cpu, 0x98, 1);
+    assert_eq!(cpu.registers.a, 0x00);
+    assert_eq!(cpu.registers.f, ZERO_FLAG | SUB_FLAG | HALF_CARRY_FLAG | CARRY_FLAG);
 }
 
 #[test]
@@ -334,6 +401,7 @@ fn and_b() {
     cpu.registers.b = 0b0001_0001;
     step(&mut cpu, 0xA0, 1);
     assert_eq!(cpu.registers.a, 0b0000_0001);
+    assert_eq!(cpu.registers.f, HALF_CARRY_FLAG);
 }
 
 #[test]
@@ -392,6 +460,16 @@ fn pop_hl() {
     assert_eq!(cpu.registers.sp, 0xFFFC + 2);
 }
 
+#[test]
+fn pop_af() {
+    let mut cpu = reset();
+    cpu.store_word(0xFFFC, 0x12FF);
+    cpu.registers.sp = 0xFFFC;
+    step(&mut cpu, 0xF1, 1);
+    assert_eq!(cpu.registers.a, 0x12);
+    assert_eq!(cpu.registers.f, 0xF0);
+}
+
 #[test]
 fn ret() {
     let mut cpu = reset();
@@ -405,9 +483,10 @@ fn ret() {
 fn rlca() {
     let mut cpu = reset();
     cpu.registers.a = 0b1000_0010;
+    cpu.registers.set_zero(true);
     step(&mut cpu, 0x07, 1);
     assert_eq!(cpu.registers.a, 0b0000_0101);
-    assert_eq!(cpu.registers.carry(), 1);
+    assert_eq!(cpu.registers.f, CARRY_FLAG);
 
     let mut cpu = reset();
     cpu.registers.a = 0b0100_0010;
@@ -469,6 +548,7 @@ fn cpl() {
     cpu.registers.a = 0b0101_1010;
     step(&mut cpu, 0x2F, 1);
     assert_eq!(cpu.registers.a, 0b1010_0101);
+    assert_eq!(cpu.registers.f, SUB_FLAG | HALF_CARRY_FLAG);
 }
 
 #[test]
@@ -491,23 +571,40 @@ fn jp() {
 fn jr() {
     let mut cpu = reset();
     cpu.registers.pc = 0x4;
-    cpu.store_byte(0x05, -4i8 as u8);
+    cpu.bus.store(0x05, -4i8 as u8);
     step(&mut cpu, 0x18, -2);
     assert_eq!(cpu.registers.pc, 0x2);
 }
 
+// Offsets wrap around the address space from anywhere, RAM above 0x8000 included
+#[test]
+fn jr_wraps() {
+    let mut cpu = reset();
+    cpu.registers.pc = 0x8000;
+    cpu.bus.store(0x8001, -4i8 as u8);
+    step(&mut cpu, 0x18, -2);
+    assert_eq!(cpu.registers.pc, 0x7FFE);
+
+    let mut cpu = reset();
+    cpu.registers.set_carry(true);
+    cpu.registers.pc = 0xFFFC;
+    cpu.bus.store(0xFFFD, 0x10);
+    step(&mut cpu, 0x38, 0x10);
+    assert_eq!(cpu.registers.pc, 0x000E);
+}
+
 #[test]
 fn jr_cond() {
     let mut cpu = reset();
     cpu.registers.set_zero(false);
     cpu.registers.pc = 0x4;
-    cpu.store_byte(0x05, -4i8 as u8);
+    cpu.bus.store(0x05, -4i8 as u8);
     step(&mut cpu, 0x20, -2);
     assert_eq!(cpu.registers.pc, 0x02);
 
     let mut cpu = reset();
     cpu.registers.set_zero(true);
-    cpu.store_byte(0x01, 0x05);
+    cpu.bus.store(0x01, 0x05);
     step(&mut cpu, 0x20, 0x02);
     assert_eq!(cpu.registers.pc, 0x02);
 }
@@ -544,7 +641,7 @@ fn call_z() {
 fn swap_a() {
     let mut cpu = reset();
     cpu.registers.a = 0b1101_0000;
-    cpu.store_byte(0x1, 0x37);
+    cpu.bus.store(0x1, 0x37);
     step(&mut cpu, 0xCB, 1);
     assert_eq!(cpu.registers.a, 0b0000_1101);
 }
@@ -553,7 +650,7 @@ fn swap_a() {
 fn res_a() {
     let mut cpu = reset();
     cpu.registers.a = 0b0001_0001;
-    cpu.store_byte(0x1, 0x87);
+    cpu.bus.store(0x1, 0x87);
     step(&mut cpu, 0xCB, 1);
     assert_eq!(cpu.registers.a, 0b0001_0000);
 }
@@ -562,7 +659,7 @@ fn res_a() {
 fn set_a() {
     let mut cpu = reset();
     cpu.registers.a = 0b0001_0000;
-    cpu.store_byte(0x1, 0xC7);
+    cpu.bus.store(0x1, 0xC7);
     step(&mut cpu, 0xCB, 1);
     assert_eq!(cpu.registers.a, 0b0001_0001);
 }
@@ -572,14 +669,657 @@ fn bit_a() {
     let mut cpu = reset();
     cpu.registers.set_zero(false);
     cpu.registers.a = 0b0000_0000;
-    cpu.store_byte(0x1, 0x47);
+    cpu.bus.store(0x1, 0x47);
     step(&mut cpu, 0xCB, 1);
     assert!(cpu.registers.test_flag(ZERO_FLAG));
 
     let mut cpu = reset();
     cpu.registers.set_zero(false);
     cpu.registers.a = 0b0000_0001;
-    cpu.store_byte(0x1, 0x47);
+    cpu.bus.store(0x1, 0x47);
     step(&mut cpu, 0xCB, 1);
     assert!(!cpu.registers.test_flag(ZERO_FLAG));
 }
+
+#[test]
+fn rlc_b() {
+    let mut cpu = reset();
+    cpu.registers.b = 0b1000_0001;
+    cpu.bus.store(0x1, 0x00);
+    step(&mut cpu, 0xCB, 2);
+    assert_eq!(cpu.registers.b, 0b0000_0011);
+    assert_eq!(cpu.registers.carry(), 1);
+    assert!(!cpu.registers.test_flag(ZERO_FLAG));
+}
+
+#[test]
+fn rl_c() {
+    let mut cpu = reset();
+    cpu.registers.c = 0b1000_0000;
+    cpu.bus.store(0x1, 0x11);
+    step(&mut cpu, 0xCB, 2);
+    assert_eq!(cpu.registers.c, 0);
+    assert_eq!(cpu.registers.carry(), 1);
+    assert!(cpu.registers.test_flag(ZERO_FLAG));
+}
+
+#[test]
+fn rr_hl() {
+    let mut cpu = reset();
+    cpu.registers.store_16(Register16::HL, 0x1234);
+    cpu.bus.store(0x1234, 0b0000_0010);
+    cpu.registers.set_carry(true);
+    cpu.bus.store(0x1, 0x1E);
+    step(&mut cpu, 0xCB, 2);
+    assert_eq!(cpu.bus.load(0x1234), 0b1000_0001);
+    assert_eq!(cpu.registers.carry(), 0);
+}
+
+#[test]
+fn sra_d() {
+    let mut cpu = reset();
+    cpu.registers.d = 0b1000_0011;
+    cpu.bus.store(0x1, 0x2A);
+    step(&mut cpu, 0xCB, 2);
+    assert_eq!(cpu.registers.d, 0b1100_0001);
+    assert_eq!(cpu.registers.carry(), 1);
+}
+
+#[test]
+fn srl_e() {
+    let mut cpu = reset();
+    cpu.registers.e = 0b1000_0010;
+    cpu.bus.store(0x1, 0x3B);
+    step(&mut cpu, 0xCB, 2);
+    assert_eq!(cpu.registers.e, 0b0100_0001);
+    assert_eq!(cpu.registers.carry(), 0);
+}
+
+#[test]
+fn swap_hl() {
+    let mut cpu = reset();
+    cpu.registers.store_16(Register16::HL, 0x1234);
+    cpu.bus.store(0x1234, 0xF1);
+    cpu.bus.store(0x1, 0x36);
+    step(&mut cpu, 0xCB, 2);
+    assert_eq!(cpu.bus.load(0x1234), 0x1F);
+}
+
+#[test]
+fn bit_7_h() {
+    let mut cpu = reset();
+    cpu.registers.set_carry(true);
+    cpu.registers.h = 0b1000_0000;
+    cpu.bus.store(0x1, 0x7C);
+    step(&mut cpu, 0xCB, 2);
+    assert_eq!(cpu.registers.f, HALF_CARRY_FLAG | CARRY_FLAG);
+}
+
+#[test]
+fn res_set_hl() {
+    let mut cpu = reset();
+    cpu.registers.store_16(Register16::HL, 0x1234);
+    cpu.bus.store(0x1234, 0b0000_1000);
+    cpu.bus.store(0x1, 0x9E); // RES 3,(HL)
+    step(&mut cpu, 0xCB, 2);
+    assert_eq!(cpu.bus.load(0x1234), 0);
+
+    cpu.registers.pc = 0;
+    cpu.bus.store(0x1, 0xFE); // SET 7,(HL)
+    step(&mut cpu, 0xCB, 2);
+    assert_eq!(cpu.bus.load(0x1234), 0b1000_0000);
+}
+
+// Reference DAA, written as the sequential adjustment described in the Pan Docs.
+// Returns the expected A and F for every A/N/H/C input.
+fn daa_reference_table() -> Vec<(u8, u8)> {
+    let mut table = Vec::with_capacity(2048);
+
+    for flags in 0..8u8 {
+        for a in 0..256u16 {
+            let n = flags & 0b100 != 0;
+            let h = flags & 0b010 != 0;
+            let c = flags & 0b001 != 0;
+            let mut value = a as u8;
+            let mut carry = c;
+
+            if !n {
+                if c || value > 0x99 { value = value.wrapping_add(0x60); carry = true; }
+                if h || (value & 0x0F) > 0x09 { value = value.wrapping_add(0x06); }
+            } else {
+                if c { value = value.wrapping_sub(0x60); }
+                if h { value = value.wrapping_sub(0x06); }
+            }
+
+            let mut f = 0;
+            if value == 0 { f |= ZERO_FLAG; }
+            if n { f |= SUB_FLAG; }
+            if carry { f |= CARRY_FLAG; }
+            table.push((value, f));
+        }
+    }
+
+    table
+}
+
+#[test]
+fn daa_exhaustive() {
+    let table = daa_reference_table();
+    let mut cpu = reset();
+
+    for flags in 0..8u8 {
+        for a in 0..256u16 {
+            let mut f = 0;
+            if flags & 0b100 != 0 { f |= SUB_FLAG; }
+            if flags & 0b010 != 0 { f |= HALF_CARRY_FLAG; }
+            if flags & 0b001 != 0 { f |= CARRY_FLAG; }
+
+            cpu.registers.pc = 0;
+            cpu.registers.a = a as u8;
+            cpu.registers.f = f;
+            step(&mut cpu, 0x27, 1);
+
+            let (expected_a, expected_f) = table[flags as usize * 256 + a as usize];
+            assert_eq!((cpu.registers.a, cpu.registers.f), (expected_a, expected_f),
+                       "DAA with A={:02X} F={:02X}", a, f);
+        }
+    }
+}
+
+#[test]
+fn cycles() {
+    let mut cpu = reset();
+    cpu.bus.store(0x0, 0x00); // NOP
+    assert_eq!(cpu.step().unwrap().cycles, 4);
+
+    let mut cpu = reset();
+    cpu.bus.store(0x0, 0xCD); // CALL nn
+    assert_eq!(cpu.step().unwrap().cycles, 24);
+
+    let mut cpu = reset();
+    cpu.bus.store(0x0, 0x34); // INC (HL)
+    assert_eq!(cpu.step().unwrap().cycles, 12);
+    assert_eq!(cpu.cycles, 12);
+}
+
+#[test]
+fn cycles_conditionals() {
+    // JR NZ: 12 when taken, 8 otherwise
+    let mut cpu = reset();
+    cpu.bus.store(0x0, 0x20);
+    assert_eq!(cpu.step().unwrap().cycles, 12);
+    let mut cpu = reset();
+    cpu.registers.set_zero(true);
+    cpu.bus.store(0x0, 0x20);
+    assert_eq!(cpu.step().unwrap().cycles, 8);
+
+    // JP Z: 16 when taken, 12 otherwise
+    let mut cpu = reset();
+    cpu.registers.set_zero(true);
+    cpu.bus.store(0x0, 0xCA);
+    assert_eq!(cpu.step().unwrap().cycles, 16);
+    let mut cpu = reset();
+    cpu.bus.store(0x0, 0xCA);
+    assert_eq!(cpu.step().unwrap().cycles, 12);
+
+    // CALL C: 24 when taken, 12 otherwise
+    let mut cpu = reset();
+    cpu.registers.set_carry(true);
+    cpu.bus.store(0x0, 0xDC);
+    assert_eq!(cpu.step().unwrap().cycles, 24);
+    let mut cpu = reset();
+    cpu.bus.store(0x0, 0xDC);
+    assert_eq!(cpu.step().unwrap().cycles, 12);
+
+    // RET NC: 20 when taken, 8 otherwise
+    let mut cpu = reset();
+    cpu.bus.store(0x0, 0xD0);
+    assert_eq!(cpu.step().unwrap().cycles, 20);
+    let mut cpu = reset();
+    cpu.registers.set_carry(true);
+    cpu.bus.store(0x0, 0xD0);
+    assert_eq!(cpu.step().unwrap().cycles, 8);
+}
+
+#[test]
+fn cycles_cb() {
+    let mut cpu = reset();
+    cpu.bus.store(0x0, 0xCB);
+    cpu.bus.store(0x1, 0x11); // RL C
+    assert_eq!(cpu.step().unwrap().cycles, 8);
+
+    let mut cpu = reset();
+    cpu.bus.store(0x0, 0xCB);
+    cpu.bus.store(0x1, 0x46); // BIT 0,(HL)
+    assert_eq!(cpu.step().unwrap().cycles, 12);
+
+    let mut cpu = reset();
+    cpu.bus.store(0x0, 0xCB);
+    cpu.bus.store(0x1, 0xC6); // SET 0,(HL)
+    assert_eq!(cpu.step().unwrap().cycles, 16);
+}
+
+// Runs every opcode with zeroed operands and flags, which takes NZ and NC branches
+#[test]
+fn opcode_table() {
+    for (instruction, opcode) in OPCODES.iter().enumerate() {
+        let unconditional = ["JP", "JR", "CALL", "RET", "RETI", "RST"].iter()
+            .any(|m| opcode.mnemonic.split(' ').next() == Some(m));
+        if instruction == 0xCB || (unconditional && opcode.cycles == opcode.branch_cycles) {
+            continue;
+        }
+
+        let mut cpu = reset();
+        cpu.registers.pc = 0x100;
+        cpu.registers.sp = 0xFFFE;
+        cpu.bus.store(0x100, instruction as u8);
+        let taken = opcode.mnemonic.contains(" NZ") || opcode.mnemonic.contains(" NC");
+        let cycles = cpu.step().unwrap().cycles;
+
+        if taken {
+            assert_eq!(cycles, opcode.branch_cycles as u64, "{}", opcode.mnemonic);
+        } else {
+            assert_eq!(cycles, opcode.cycles as u64, "{}", opcode.mnemonic);
+            assert_eq!(cpu.registers.pc, 0x100 + opcode.length as u16, "{}", opcode.mnemonic);
+        }
+    }
+
+    for (instruction, opcode) in CB_OPCODES.iter().enumerate() {
+        let mut cpu = reset();
+        cpu.registers.pc = 0x100;
+        cpu.bus.store(0x100, 0xCB);
+        cpu.bus.store(0x101, instruction as u8);
+        assert_eq!(cpu.step().unwrap().cycles, opcode.cycles as u64, "{}", opcode.mnemonic);
+        assert_eq!(cpu.registers.pc, 0x100 + opcode.length as u16, "{}", opcode.mnemonic);
+    }
+}
+
+// The decoder reads as many operand bytes as the table says, low byte first
+#[test]
+fn operands_from_table() {
+    for (instruction, opcode) in OPCODES.iter().enumerate() {
+        if opcode.is_illegal() {
+            continue;
+        }
+
+        let mut cpu = reset();
+        cpu.registers.pc = 0x100;
+        cpu.registers.sp = 0xFFFE;
+        cpu.bus.store(0x100, instruction as u8);
+        cpu.bus.store(0x101, 0x34);
+        cpu.bus.store(0x102, 0x12);
+        cpu.step().unwrap();
+
+        let expected = match opcode.operand {
+            Operand::None => 0,
+            Operand::Word => 0x1234,
+            _ => 0x34,
+        };
+        assert_eq!(cpu.operand, expected, "{}", opcode.mnemonic);
+    }
+}
+
+#[test]
+fn ei_delay() {
+    let mut cpu = reset();
+    cpu.bus.store(0xFFFF, 0x01);
+    cpu.bus.store(0xFF0F, 0x01);
+    cpu.bus.store(0x0, 0xFB); // EI
+    cpu.bus.store(0x1, 0x00); // NOP
+    cpu.bus.store(0x2, 0x00); // NOP
+
+    cpu.step().unwrap();
+    assert!(!cpu.ime);
+    cpu.step().unwrap(); // The instruction after EI still runs uninterrupted
+    assert_eq!(cpu.registers.pc, 0x2);
+    assert!(cpu.ime);
+
+    assert_eq!(cpu.step().unwrap().cycles, 20);
+    assert_eq!(cpu.registers.pc, 0x40);
+    assert_eq!(cpu.bus.load(0xFF0F), 0);
+    assert!(!cpu.ime);
+    assert_eq!(cpu.pop_word(), 0x2);
+}
+
+#[test]
+fn ei_di() {
+    let mut cpu = reset();
+    cpu.bus.store(0xFFFF, 0x01);
+    cpu.bus.store(0xFF0F, 0x01);
+    cpu.bus.store(0x0, 0xFB); // EI
+    cpu.bus.store(0x1, 0xF3); // DI
+    cpu.bus.store(0x2, 0x00); // NOP
+
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+    assert!(!cpu.ime);
+    assert_eq!(cpu.registers.pc, 0x3);
+}
+
+#[test]
+fn interrupt_priority() {
+    let mut cpu = reset();
+    cpu.ime = true;
+    cpu.bus.store(0xFFFF, 0x1F);
+    cpu.bus.store(0xFF0F, 0b0001_0100); // Timer and Joypad
+    cpu.step().unwrap();
+    assert_eq!(cpu.registers.pc, 0x50);
+    assert_eq!(cpu.bus.load(0xFF0F), 0b0001_0000);
+
+    cpu.ime = true;
+    cpu.step().unwrap();
+    assert_eq!(cpu.registers.pc, 0x60);
+
+    // Disabled interrupts are not serviced
+    let mut cpu = reset();
+    cpu.ime = true;
+    cpu.bus.store(0xFFFF, 0b0000_1000);
+    cpu.bus.store(0xFF0F, 0b0000_1010);
+    cpu.step().unwrap();
+    assert_eq!(cpu.registers.pc, 0x58);
+}
+
+#[test]
+fn reti() {
+    let mut cpu = reset();
+    cpu.push_word(0x1234);
+    step(&mut cpu, 0xD9, 0x1234);
+    assert_eq!(cpu.registers.pc, 0x1234);
+    assert!(cpu.ime);
+}
+
+#[test]
+fn halt() {
+    let mut cpu = reset();
+    cpu.bus.store(0xFFFF, 0x04);
+    cpu.bus.store(0x0, 0x76); // HALT
+    cpu.bus.store(0x1, 0x3C); // INC A
+
+    cpu.step().unwrap();
+    assert!(cpu.halt);
+    assert_eq!(cpu.step().unwrap().cycles, 4);
+    assert_eq!(cpu.registers.pc, 0x1);
+
+    // Wakes up without servicing the interrupt since IME is off
+    cpu.bus.store(0xFF0F, 0x04);
+    cpu.step().unwrap();
+    assert!(!cpu.halt);
+    assert_eq!(cpu.registers.pc, 0x2);
+    assert_eq!(cpu.registers.a, 0x1);
+}
+
+#[test]
+fn halt_ime() {
+    let mut cpu = reset();
+    cpu.ime = true;
+    cpu.bus.store(0xFFFF, 0x01);
+    cpu.bus.store(0x0, 0x76); // HALT
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+    assert!(cpu.halt);
+
+    cpu.bus.store(0xFF0F, 0x01);
+    cpu.step().unwrap();
+    assert_eq!(cpu.registers.pc, 0x40);
+    assert_eq!(cpu.pop_word(), 0x1);
+}
+
+#[test]
+fn halt_bug() {
+    let mut cpu = reset();
+    cpu.bus.store(0xFFFF, 0x01);
+    cpu.bus.store(0xFF0F, 0x01);
+    cpu.bus.store(0x0, 0x76); // HALT
+    cpu.bus.store(0x1, 0x3C); // INC A
+    cpu.bus.store(0x2, 0x00); // NOP
+
+    cpu.step().unwrap();
+    assert!(!cpu.halt);
+    cpu.step().unwrap();
+    assert_eq!(cpu.registers.pc, 0x1);
+    cpu.step().unwrap();
+    assert_eq!(cpu.registers.pc, 0x2);
+    assert_eq!(cpu.registers.a, 0x2);
+}
+
+#[test]
+fn timer_interrupt() {
+    let mut cpu = gameboy();
+    cpu.bus.store(0xFF06, 0x42); // TMA
+    cpu.bus.store(0xFF05, 0xFF); // TIMA
+    cpu.bus.store(0xFF07, 0x05); // Enabled, every 16 cycles
+    for _ in 0..4 {
+        step(&mut cpu, 0x00, 1);
+    }
+    assert_eq!(cpu.bus.load(0xFF05), 0x42);
+    assert_eq!(cpu.bus.interrupt_flags, 0x04);
+}
+
+// The read happens on the last machine cycle, after the timer ticked
+#[test]
+fn timer_mid_instruction() {
+    let mut cpu = gameboy();
+    cpu.bus.store(0xFF07, 0x05); // Enabled, every 16 cycles
+    cpu.bus.store(0xFF04, 0x00);
+    cpu.bus.store(0xC000, 0x00); // NOP
+    cpu.bus.store(0xC001, 0xF0); // LDH A,($05)
+    cpu.bus.store(0xC002, 0x05);
+
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+    assert_eq!(cpu.registers.a, 0x01);
+}
+
+#[test]
+fn push_timing() {
+    // The high byte of PC is written first, after an internal cycle
+    let mut cpu = gameboy();
+    cpu.registers.sp = 0xFFFE;
+    cpu.bus.store(0xC000, 0xCD); // CALL $C123
+    cpu.bus.store(0xC001, 0x23);
+    cpu.bus.store(0xC002, 0xC1);
+    assert_eq!(cpu.step().unwrap().cycles, 24);
+    assert_eq!(cpu.bus.load(0xFFFD), 0xC0);
+    assert_eq!(cpu.bus.load(0xFFFC), 0x03);
+    assert_eq!(cpu.registers.pc, 0xC123);
+}
+
+#[test]
+fn stop() {
+    let mut cpu = gameboy();
+    cpu.bus.timer.counter = 0x1234;
+    cpu.bus.store(0xFF00, 0x10); // Select the buttons
+    cpu.bus.store(0xC000, 0x10); // STOP
+    cpu.bus.store(0xC001, 0x00);
+    cpu.bus.store(0xC002, 0x3C); // INC A
+
+    cpu.step().unwrap();
+    assert!(cpu.stopped);
+    assert_eq!(cpu.registers.pc, 0xC002);
+    assert_eq!(cpu.bus.load(0xFF04), 0);
+
+    // Nothing happens until a button is pressed, not even the LCD
+    let ly = cpu.bus.gpu.ly;
+    for _ in 0..1000 {
+        cpu.step().unwrap();
+    }
+    assert_eq!(cpu.registers.pc, 0xC002);
+    assert_eq!(cpu.bus.gpu.ly, ly);
+
+    // Directions are not selected
+    cpu.bus.joypad.press(Button::Up);
+    cpu.step().unwrap();
+    assert!(cpu.stopped);
+
+    cpu.bus.joypad.press(Button::Start);
+    cpu.step().unwrap();
+    assert!(!cpu.stopped);
+    assert_eq!(cpu.registers.a, 0x1);
+}
+
+#[test]
+fn stop_speed_switch() {
+    let mut cpu = gameboy();
+    cpu.bus.cgb = true;
+    cpu.bus.store(0xFF4D, 0x01);
+    assert_eq!(cpu.bus.load(0xFF4D), 0x7F);
+    cpu.bus.store(0xC000, 0x10); // STOP
+    cpu.bus.store(0xC001, 0x00);
+
+    cpu.step().unwrap();
+    assert!(!cpu.stopped);
+    assert!(cpu.bus.double_speed);
+    assert_eq!(cpu.bus.load(0xFF4D), 0xFE);
+}
+
+#[test]
+fn illegal_opcode_lockup() {
+    let mut cpu = reset();
+    cpu.ime = true;
+    cpu.bus.store(0xFFFF, 0x01);
+    cpu.bus.store(0x0, 0xD3);
+    cpu.step().unwrap();
+    assert!(cpu.locked);
+
+    // Not even interrupts get the CPU going again
+    cpu.bus.store(0xFF0F, 0x01);
+    assert_eq!(cpu.step().unwrap().cycles, 4);
+    assert_eq!(cpu.registers.pc, 0x1);
+}
+
+#[test]
+fn illegal_opcode_error() {
+    let mut cpu = reset();
+    cpu.illegal_opcode_policy = IllegalOpcodePolicy::Error;
+    cpu.registers.pc = 0x10;
+    cpu.bus.store(0x10, 0xFD);
+    assert_eq!(cpu.step(), Err(EmuError::IllegalOpcode { pc: 0x10, opcode: 0xFD }));
+    assert_eq!(cpu.registers.pc, 0x10);
+    assert_eq!(cpu.cycles, 4); // Only the fetch went through
+}
+
+#[test]
+fn illegal_opcode_break() {
+    let mut cpu = reset();
+    cpu.illegal_opcode_policy = IllegalOpcodePolicy::Break;
+    cpu.bus.store(0x0, 0xE4);
+    cpu.step().unwrap();
+    assert!(cpu.breakpoint);
+    assert!(!cpu.locked);
+    assert_eq!(cpu.registers.pc, 0x0);
+
+    // Nothing happens until the breakpoint is cleared
+    let cycles = cpu.cycles;
+    assert_eq!(cpu.step(), Ok(StepInfo { pc: 0x0, opcode: None, cycles: 0 }));
+    assert_eq!(cpu.cycles, cycles);
+    assert_eq!(cpu.bus.cycles, cycles);
+
+    cpu.breakpoint = false;
+    cpu.bus.store(0x0, 0x00);
+    assert_eq!(cpu.step(), Ok(StepInfo { pc: 0x0, opcode: Some(0x00), cycles: 4 }));
+}
+
+#[test]
+fn illegal_opcode_policy_names() {
+    assert_eq!(IllegalOpcodePolicy::from_name("lockup"), Some(IllegalOpcodePolicy::Lockup));
+    assert_eq!(IllegalOpcodePolicy::from_name("error"), Some(IllegalOpcodePolicy::Error));
+    assert_eq!(IllegalOpcodePolicy::from_name("break"), Some(IllegalOpcodePolicy::Break));
+    assert_eq!(IllegalOpcodePolicy::from_name("ignore"), None);
+}
+
+#[test]
+fn ld_b_b() {
+    let mut cpu = reset();
+    step(&mut cpu, 0x40, 1);
+    assert!(!cpu.breakpoint);
+
+    cpu.ld_b_b_breakpoint = true;
+    step(&mut cpu, 0x40, 1);
+    assert!(cpu.breakpoint);
+    assert_eq!(cpu.registers.pc, 0x2);
+}
+
+#[test]
+fn step_info() {
+    let mut cpu = reset();
+    cpu.registers.pc = 0x10;
+    cpu.bus.store(0x10, 0x3C); // INC A
+    let info = cpu.step().unwrap();
+    assert_eq!(info, StepInfo { pc: 0x10, opcode: Some(0x3C), cycles: 4 });
+
+    cpu.ime = true;
+    cpu.bus.store(0xFFFF, 0x01);
+    cpu.bus.store(0xFF0F, 0x01);
+    let info = cpu.step().unwrap();
+    assert_eq!(info, StepInfo { pc: 0x11, opcode: None, cycles: 20 });
+}
+
+#[test]
+fn unmapped_read() {
+    let mut cpu = gameboy();
+    cpu.registers.store_16(Register16::HL, 0xFF03);
+    cpu.bus.store(0xC000, 0x7E); // LD A,(HL)
+    cpu.bus.store(0xC001, 0x7E);
+    assert!(cpu.step().is_ok());
+    assert_eq!(cpu.registers.a, 0xFF);
+
+    // Strict mode reports it
+    cpu.bus.strict = true;
+    cpu.registers.a = 0;
+    assert_eq!(cpu.step(), Err(EmuError::UnmappedRead { pc: 0xC001, opcode: 0x7E, address: 0xFF03 }));
+
+    // The machine is still there to look at, and can keep going
+    assert_eq!(cpu.registers.pc, 0xC002);
+    assert_eq!(cpu.registers.a, 0xFF);
+    cpu.bus.store(0xC002, 0x00);
+    assert!(cpu.step().is_ok());
+}
+
+#[test]
+fn unmapped_write() {
+    let mut cpu = gameboy();
+    cpu.registers.a = 0x42;
+    cpu.bus.store(0xC000, 0xE0); // LDH ($03),A
+    cpu.bus.store(0xC001, 0x03);
+    cpu.bus.store(0xC002, 0xE0);
+    cpu.bus.store(0xC003, 0x03);
+    assert!(cpu.step().is_ok());
+
+    cpu.bus.strict = true;
+    assert_eq!(cpu.step(), Err(EmuError::UnmappedWrite { pc: 0xC002, opcode: 0xE0, address: 0xFF03, value: 0x42 }));
+}
+
+#[test]
+fn unused_io() {
+    let mut cpu = gameboy();
+    cpu.bus.store(0xC000, 0xF0); // LDH A,($7F)
+    cpu.bus.store(0xC001, 0x7F);
+    cpu.bus.store(0xC002, 0xF0);
+    cpu.bus.store(0xC003, 0x7F);
+    assert!(cpu.step().is_ok());
+    assert_eq!(cpu.registers.a, 0xFF);
+
+    cpu.bus.strict = true;
+    assert_eq!(cpu.step(), Err(EmuError::UnmappedRead { pc: 0xC002, opcode: 0xF0, address: 0xFF7F }));
+
+    // Writes are ignored, and reported in strict mode too
+    cpu.bus.store(0xFF7F, 0x42);
+    assert_eq!(cpu.bus.take_fault(), Some(BusFault::Write(0xFF7F, 0x42)));
+    cpu.bus.strict = false;
+    assert_eq!(cpu.bus.load(0xFF7F), 0xFF);
+
+    // KEY1 is only there on CGB
+    assert_eq!(cpu.bus.load(0xFF4D), 0xFF);
+    cpu.bus.cgb = true;
+    assert_eq!(cpu.bus.load(0xFF4D), 0x7E);
+}
+
+#[test]
+fn echo_ram() {
+    let mut cpu = gameboy();
+    cpu.bus.store(0xC123, 0x42);
+    assert_eq!(cpu.bus.load(0xE123), 0x42);
+    cpu.bus.store(0xFDFF, 0x24);
+    assert_eq!(cpu.bus.load(0xDDFF), 0x24);
+}
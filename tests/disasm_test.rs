@@ -0,0 +1,65 @@
+extern crate yob;
+
+use yob::bus::{Bus, FlatBus};
+use yob::disasm::{disassemble, disassemble_at, Instruction, Symbols};
+
+fn text(bytes: &[u8], address: u16) -> (String, u8) {
+    let instruction = disassemble(bytes, address, None);
+    (instruction.text, instruction.length)
+}
+
+#[test]
+fn operands() {
+    assert_eq!(text(&[0x00], 0), ("NOP".to_string(), 1));
+    assert_eq!(text(&[0x2A], 0), ("LD A,(HL+)".to_string(), 1));
+    assert_eq!(text(&[0x3E, 0x42], 0), ("LD A,$42".to_string(), 2));
+    assert_eq!(text(&[0x01, 0x34, 0x12], 0), ("LD BC,$1234".to_string(), 3));
+    assert_eq!(text(&[0x08, 0x00, 0xC0], 0), ("LD ($C000),SP".to_string(), 3));
+    assert_eq!(text(&[0xC3, 0x50, 0x01], 0), ("JP $0150".to_string(), 3));
+    assert_eq!(text(&[0xE0, 0x44], 0), ("LDH ($FF44),A".to_string(), 2));
+    assert_eq!(text(&[0xE8, 0x05], 0), ("ADD SP,$05".to_string(), 2));
+    assert_eq!(text(&[0xE8, 0xFE], 0), ("ADD SP,-$02".to_string(), 2));
+    assert_eq!(text(&[0xF8, 0xFE], 0), ("LD HL,SP-$02".to_string(), 2));
+    assert_eq!(text(&[0x10, 0x00], 0), ("STOP".to_string(), 2));
+    assert_eq!(text(&[0xD3], 0), ("DB $D3".to_string(), 1));
+}
+
+#[test]
+fn relative_jumps() {
+    assert_eq!(text(&[0x20, 0x10], 0x0140), ("JR NZ,$0152".to_string(), 2));
+    assert_eq!(text(&[0x18, 0xFE], 0x0150), ("JR $0150".to_string(), 2));
+    assert_eq!(text(&[0x38, 0x80], 0x0000), ("JR C,$FF82".to_string(), 2));
+}
+
+#[test]
+fn cb_page() {
+    assert_eq!(text(&[0xCB, 0x37], 0), ("SWAP A".to_string(), 2));
+    assert_eq!(text(&[0xCB, 0x7E], 0), ("BIT 7,(HL)".to_string(), 2));
+    assert_eq!(text(&[0xCB, 0xC8], 0), ("SET 1,B".to_string(), 2));
+}
+
+#[test]
+fn symbols() {
+    let symbols = Symbols::parse("; File generated by rgblink\n\
+                                  00:0150 Main\n\
+                                  00:ff44 rLY\n\
+                                  garbage\n");
+    let disassemble = |bytes: &[u8], address| disassemble(bytes, address, Some(&symbols)).text;
+    assert_eq!(disassemble(&[0xCD, 0x50, 0x01], 0x100), "CALL Main");
+    assert_eq!(disassemble(&[0x18, 0xFE], 0x150), "JR Main");
+    assert_eq!(disassemble(&[0xF0, 0x44], 0x100), "LDH A,(rLY)");
+    assert_eq!(disassemble(&[0xC3, 0x00, 0x02], 0x100), "JP $0200");
+
+    // Data is left alone even if it happens to match
+    assert_eq!(disassemble(&[0x21, 0x50, 0x01], 0x100), "LD HL,$0150");
+}
+
+#[test]
+fn from_bus() {
+    let mut bus = FlatBus::new();
+    bus.store(0xFFFF, 0xC4);
+    bus.store(0x0000, 0x34);
+    bus.store(0x0001, 0x12);
+    let instruction = disassemble_at(&mut bus, 0xFFFF, None);
+    assert_eq!(instruction, Instruction { text: "CALL NZ,$1234".to_string(), length: 3 });
+}
@@ -0,0 +1,104 @@
+extern crate yob;
+
+use yob::gpu::Gpu;
+use yob::memory::{STAT_INTERRUPT, VBLANK_INTERRUPT};
+
+// At the start of line 0, with the LCD just turned on
+fn gpu() -> Gpu {
+    let mut gpu = Gpu::new();
+    gpu.store(0x40, 0x00);
+    gpu.store(0x40, 0x91);
+    gpu
+}
+
+// Runs the LCD in machine cycles until it enters the STAT mode asked for
+fn run_until_mode(gpu: &mut Gpu, mode: u8) -> u64 {
+    let mut cycles = 0;
+    loop {
+        let before = gpu.load(0x41) & 0x03;
+        gpu.step(4);
+        cycles += 4;
+        if before != mode && gpu.load(0x41) & 0x03 == mode {
+            return cycles;
+        }
+        assert!(cycles < 70224, "never got to mode {}", mode);
+    }
+}
+
+#[test]
+fn modes() {
+    let mut gpu = gpu();
+    gpu.step(4);
+    assert_eq!(gpu.load(0x41) & 0x03, 2);
+    assert_eq!(run_until_mode(&mut gpu, 3), 76);
+    assert_eq!(run_until_mode(&mut gpu, 0), 172);
+    assert_eq!(run_until_mode(&mut gpu, 2), 204);
+    assert_eq!(gpu.ly, 1);
+
+    run_until_mode(&mut gpu, 1);
+    assert_eq!(gpu.ly, 144);
+    assert_eq!(gpu.interrupts, VBLANK_INTERRUPT);
+
+    // The whole of VBlank, back to line 0
+    assert_eq!(run_until_mode(&mut gpu, 2), 10 * 456);
+    assert_eq!(gpu.ly, 0);
+}
+
+#[test]
+fn mode_bits_read_only() {
+    let mut gpu = gpu();
+    gpu.step(4);
+    gpu.store(0x41, 0xFF);
+    assert_eq!(gpu.load(0x41) & 0x03, 2);
+    gpu.store(0x41, 0x00);
+    assert_eq!(gpu.load(0x41) & 0x03, 2);
+
+    // Nothing runs with the LCD off
+    gpu.store(0x40, 0x00);
+    assert_eq!(gpu.load(0x41) & 0x03, 0);
+}
+
+// Each mode interrupt only fires when its STAT bit is set
+fn stat_source(enable: u8, mode: u8) {
+    let mut lcd = gpu();
+    lcd.step(4);
+    run_until_mode(&mut lcd, mode);
+    assert_eq!(lcd.interrupts & STAT_INTERRUPT, 0, "mode {}", mode);
+
+    let mut lcd = gpu();
+    lcd.store(0x41, enable);
+    lcd.step(4);
+    lcd.interrupts = 0;
+    run_until_mode(&mut lcd, mode);
+    assert_eq!(lcd.interrupts & STAT_INTERRUPT, STAT_INTERRUPT, "mode {}", mode);
+}
+
+#[test]
+fn hblank_interrupt() {
+    stat_source(0x08, 0);
+}
+
+#[test]
+fn vblank_interrupt() {
+    stat_source(0x10, 1);
+}
+
+#[test]
+fn oam_interrupt() {
+    stat_source(0x20, 2);
+}
+
+#[test]
+fn lyc_interrupt() {
+    let mut gpu = gpu();
+    gpu.store(0x45, 2);
+    gpu.store(0x41, 0x40);
+    run_until_mode(&mut gpu, 0);
+    assert_eq!(gpu.interrupts & STAT_INTERRUPT, 0);
+
+    while gpu.ly != 2 {
+        gpu.step(4);
+    }
+    assert_eq!(gpu.load(0x41) & 0x04, 0x04);
+    assert_eq!(gpu.interrupts & STAT_INTERRUPT, STAT_INTERRUPT);
+}
@@ -0,0 +1,43 @@
+extern crate yob;
+
+use yob::joypad::{Button, Joypad};
+
+#[test]
+fn nothing_pressed() {
+    let mut joypad = Joypad::new();
+    joypad.store(0x00);
+    assert_eq!(joypad.load(), 0xCF);
+}
+
+#[test]
+fn select_directions() {
+    let mut joypad = Joypad::new();
+    joypad.press(Button::Down);
+    joypad.press(Button::A);
+    joypad.store(0x20);
+    assert_eq!(joypad.load(), 0xE0 | 0b0111);
+    joypad.store(0x10);
+    assert_eq!(joypad.load(), 0xD0 | 0b1110);
+    joypad.store(0x30);
+    assert_eq!(joypad.load(), 0xFF);
+}
+
+#[test]
+fn release() {
+    let mut joypad = Joypad::new();
+    joypad.store(0x10);
+    joypad.press(Button::Start);
+    assert_eq!(joypad.lines(), 0b0111);
+    joypad.release(Button::Start);
+    assert_eq!(joypad.lines(), 0b1111);
+}
+
+#[test]
+fn interrupt() {
+    let mut joypad = Joypad::new();
+    joypad.store(0x20);
+    joypad.press(Button::B);
+    assert!(!joypad.interrupt);
+    joypad.press(Button::Left);
+    assert!(joypad.interrupt);
+}
@@ -0,0 +1,561 @@
+extern crate yob;
+
+mod common;
+
+use yob::bus::Bus;
+use yob::cartridge::{Cartridge, Header, LOGO};
+use yob::mapper::{Kind, Rtc};
+use yob::memory::Memory;
+
+// A cartridge with every bank starting with its own number
+fn cartridge(cartridge_type: u8, rom_size_code: u8, ram_size_code: u8) -> Cartridge {
+    let mut rom = common::rom(cartridge_type, rom_size_code);
+    for bank in 0..rom.len() / 0x4000 {
+        rom[bank * 0x4000] = bank as u8;
+        rom[bank * 0x4000 + 0x3FFF] = bank as u8;
+    }
+    rom[0x149] = ram_size_code;
+    common::cartridge(rom)
+}
+
+#[test]
+fn rom_only() {
+    let mut memory = Memory::new(cartridge(0x00, 0x00, 0x00));
+    memory.store(0x4000, 0x12);
+    assert_eq!(memory.load(0x4000), 0x01);
+    assert_eq!(memory.load(0xA000), 0xFF);
+}
+
+#[test]
+fn mbc1_rom_banking() {
+    // 2 MB, all 7 bits of the bank number are in use
+    let mut memory = Memory::new(cartridge(0x01, 0x06, 0x00));
+    assert_eq!(memory.load(0x4000), 0x01);
+
+    memory.store(0x2000, 0x05);
+    assert_eq!(memory.load(0x4000), 0x05);
+    assert_eq!(memory.load(0x7FFF), 0x05);
+
+    // Bank 0 reads as bank 1, and so do the ones with the low 5 bits clear
+    memory.store(0x2000, 0x00);
+    assert_eq!(memory.load(0x4000), 0x01);
+    memory.store(0x2000, 0x20);
+    assert_eq!(memory.load(0x4000), 0x01);
+
+    memory.store(0x4000, 0x02);
+    memory.store(0x2000, 0x03);
+    assert_eq!(memory.load(0x4000), 0x43);
+    assert_eq!(memory.load(0x0000), 0x00);
+
+    // Mode 1 applies the upper bits to the first bank too
+    memory.store(0x6000, 0x01);
+    assert_eq!(memory.load(0x0000), 0x40);
+    assert_eq!(memory.load(0x3FFF), 0x40);
+
+    // Writing there doesn't touch the ROM
+    memory.store(0x6000, 0x00);
+    assert_eq!(memory.load(0x0000), 0x00);
+}
+
+#[test]
+fn mbc1_bank_mask() {
+    // 256 kB, only 16 banks
+    let mut memory = Memory::new(cartridge(0x01, 0x03, 0x00));
+    memory.store(0x2000, 0x13);
+    assert_eq!(memory.load(0x4000), 0x03);
+}
+
+#[test]
+fn mbc1_ram() {
+    // 32 kB of RAM in 4 banks
+    let mut memory = Memory::new(cartridge(0x03, 0x01, 0x03));
+
+    // Disabled on startup
+    memory.store(0xA000, 0x42);
+    assert_eq!(memory.load(0xA000), 0xFF);
+
+    memory.store(0x0000, 0x0A);
+    memory.store(0xA000, 0x42);
+    assert_eq!(memory.load(0xA000), 0x42);
+
+    // Banks only switch in mode 1
+    memory.store(0x4000, 0x02);
+    assert_eq!(memory.load(0xA000), 0x42);
+    memory.store(0x6000, 0x01);
+    assert_eq!(memory.load(0xA000), 0x00);
+    memory.store(0xBFFF, 0x24);
+    memory.store(0x4000, 0x00);
+    assert_eq!(memory.load(0xA000), 0x42);
+    memory.store(0x4000, 0x02);
+    assert_eq!(memory.load(0xBFFF), 0x24);
+
+    memory.store(0x0000, 0x00);
+    assert_eq!(memory.load(0xA000), 0xFF);
+}
+
+#[test]
+fn mbc1_multicart() {
+    let mut cartridge = cartridge(0x01, 0x05, 0x00);
+    let logo: Vec<u8> = (0..0x30).collect();
+    for game in 0..4 {
+        let start = game * 0x40000 + 0x104;
+        cartridge.rom[start..start + 0x30].copy_from_slice(&logo);
+    }
+    assert_eq!(Kind::detect(&cartridge), Some(Kind::Mbc1Multicart));
+
+    let mut memory = Memory::new(cartridge);
+    memory.store(0x4000, 0x01);
+    memory.store(0x2000, 0x12);
+    assert_eq!(memory.load(0x4000), 0x12);
+
+    // Each game sees its first bank at 0x0000 in mode 1
+    memory.store(0x6000, 0x01);
+    assert_eq!(memory.load(0x0000), 0x10);
+}
+
+#[test]
+fn mbc1_not_multicart() {
+    let mut cartridge = cartridge(0x01, 0x05, 0x00);
+    let logo: Vec<u8> = (0..0x30).collect();
+    cartridge.rom[0x104..0x134].copy_from_slice(&logo);
+    assert_eq!(Kind::detect(&cartridge), Some(Kind::Mbc1));
+}
+
+#[test]
+fn mbc3_banking() {
+    // 2 MB of ROM and 32 kB of RAM
+    let mut memory = Memory::new(cartridge(0x13, 0x06, 0x03));
+    memory.store(0x2000, 0x00);
+    assert_eq!(memory.load(0x4000), 0x01);
+    memory.store(0x2000, 0x7F);
+    assert_eq!(memory.load(0x4000), 0x7F);
+    assert_eq!(memory.load(0x0000), 0x00);
+
+    memory.store(0x0000, 0x0A);
+    memory.store(0x4000, 0x03);
+    memory.store(0xA000, 0x42);
+    memory.store(0x4000, 0x00);
+    assert_eq!(memory.load(0xA000), 0x00);
+    memory.store(0x4000, 0x03);
+    assert_eq!(memory.load(0xA000), 0x42);
+
+    // No clock on this one
+    assert!(memory.mapper.rtc().is_none());
+    memory.store(0x4000, 0x08);
+    assert_eq!(memory.load(0xA000), 0xFF);
+}
+
+fn latch(memory: &mut Memory) {
+    memory.store(0x6000, 0x00);
+    memory.store(0x6000, 0x01);
+}
+
+#[test]
+fn mbc3_rtc() {
+    let mut memory = Memory::new(cartridge(0x10, 0x01, 0x03));
+    memory.store(0x0000, 0x0A);
+
+    // Set the clock to 23:59:59 on day 511
+    for &(register, value) in &[(0x08, 59), (0x09, 59), (0x0A, 23), (0x0B, 0xFF), (0x0C, 0x01)] {
+        memory.store(0x4000, register);
+        memory.store(0xA000, value);
+    }
+
+    memory.tick(4_194_304);
+    memory.store(0x4000, 0x08);
+    assert_eq!(memory.load(0xA000), 59); // Not latched yet
+
+    latch(&mut memory);
+    for &(register, value) in &[(0x08, 0), (0x09, 0), (0x0A, 0), (0x0B, 0), (0x0C, 0x80)] {
+        memory.store(0x4000, register);
+        assert_eq!(memory.load(0xA000), value, "register {:02X}", register);
+    }
+
+    // Latching needs 0 then 1
+    memory.tick(4_194_304 * 2);
+    memory.store(0x6000, 0x01);
+    memory.store(0x4000, 0x08);
+    assert_eq!(memory.load(0xA000), 0);
+    latch(&mut memory);
+    assert_eq!(memory.load(0xA000), 2);
+}
+
+#[test]
+fn mbc3_rtc_halt() {
+    let mut memory = Memory::new(cartridge(0x0F, 0x01, 0x00));
+    memory.store(0x0000, 0x0A);
+    memory.store(0x4000, 0x0C);
+    memory.store(0xA000, 0x40);
+
+    memory.tick(4_194_304 * 3);
+    memory.mapper.rtc().unwrap().advance(100);
+    latch(&mut memory);
+    memory.store(0x4000, 0x08);
+    assert_eq!(memory.load(0xA000), 0);
+}
+
+#[test]
+fn rtc_advance() {
+    let mut rtc = Rtc::new();
+    rtc.advance(86400 * 2 + 3600 * 5 + 60 * 4 + 3);
+    assert_eq!((rtc.days, rtc.hours, rtc.minutes, rtc.seconds), (2, 5, 4, 3));
+    assert!(!rtc.carry);
+
+    rtc.advance(86400 * 510);
+    assert_eq!((rtc.days, rtc.carry), (0, true));
+
+    // Invalid values wrap without carrying
+    let mut rtc = Rtc::new();
+    rtc.seconds = 62;
+    rtc.advance(2);
+    assert_eq!((rtc.minutes, rtc.seconds), (0, 0));
+    rtc.advance(61);
+    assert_eq!((rtc.minutes, rtc.seconds), (1, 1));
+}
+
+#[test]
+fn mbc5_banking() {
+    // 8 MB of ROM and 128 kB of RAM
+    let mut memory = Memory::new(cartridge(0x1B, 0x08, 0x04));
+    assert_eq!(memory.load(0x4000), 0x01);
+
+    memory.store(0x2000, 0x00);
+    assert_eq!(memory.load(0x4000), 0x00);
+    memory.store(0x2000, 0x05);
+    memory.store(0x3000, 0x01);
+    assert_eq!(memory.load(0x4000), 0x05); // Bank 0x105
+    memory.store(0x3000, 0x00);
+    assert_eq!(memory.load(0x7FFF), 0x05);
+    assert_eq!(memory.load(0x0000), 0x00);
+
+    memory.store(0x0000, 0x0A);
+    memory.store(0x4000, 0x0F);
+    memory.store(0xA000, 0x42);
+    memory.store(0x4000, 0x07);
+    assert_eq!(memory.load(0xA000), 0x00);
+    memory.store(0x4000, 0x0F);
+    assert_eq!(memory.load(0xA000), 0x42);
+    assert!(!memory.mapper.rumble());
+}
+
+#[test]
+fn mbc5_rumble() {
+    let mut memory = Memory::new(cartridge(0x1E, 0x01, 0x03));
+    memory.store(0x0000, 0x0A);
+    memory.store(0x4000, 0x01);
+    memory.store(0xA000, 0x42);
+
+    // Bit 3 drives the motor and doesn't switch banks
+    memory.store(0x4000, 0x09);
+    assert!(memory.mapper.rumble());
+    assert_eq!(memory.load(0xA000), 0x42);
+
+    memory.store(0x4000, 0x01);
+    assert!(!memory.mapper.rumble());
+}
+
+#[test]
+fn mbc5_rumble_changes() {
+    let mut memory = Memory::new(cartridge(0x1E, 0x01, 0x03));
+    assert_eq!(memory.mapper.take_rumble_change(), None);
+
+    memory.store(0x4000, 0x01);
+    assert_eq!(memory.mapper.take_rumble_change(), None);
+
+    memory.store(0x4000, 0x09);
+    assert_eq!(memory.mapper.take_rumble_change(), Some(true));
+    assert_eq!(memory.mapper.take_rumble_change(), None);
+
+    // Switching banks with the motor still on isn't a change
+    memory.store(0x4000, 0x0A);
+    assert_eq!(memory.mapper.take_rumble_change(), None);
+
+    memory.store(0x4000, 0x02);
+    assert_eq!(memory.mapper.take_rumble_change(), Some(false));
+
+    // Not wired on plain MBC5 carts
+    let mut memory = Memory::new(cartridge(0x1B, 0x01, 0x04));
+    memory.store(0x4000, 0x08);
+    assert_eq!(memory.mapper.take_rumble_change(), None);
+}
+
+#[test]
+fn mbc2() {
+    let mut memory = Memory::new(cartridge(0x06, 0x03, 0x00));
+
+    // Address bit 8 picks the register
+    memory.store(0x2100, 0x05);
+    assert_eq!(memory.load(0x4000), 0x05);
+    memory.store(0x0100, 0x00);
+    assert_eq!(memory.load(0x4000), 0x01);
+    memory.store(0x0000, 0x02);
+    assert_eq!(memory.load(0x4000), 0x01);
+    memory.store(0x3FFF, 0x13);
+    assert_eq!(memory.load(0x4000), 0x03);
+
+    assert_eq!(memory.load(0xA000), 0xFF);
+    memory.store(0x0000, 0x0A);
+    memory.store(0xA000, 0x42);
+    assert_eq!(memory.load(0xA000), 0xF2);
+    assert_eq!(memory.load(0xA200), 0xF2); // Echo of the 512 half bytes
+    assert_eq!(memory.load(0xBE00), 0xF2);
+}
+
+#[test]
+fn huc1() {
+    let mut memory = Memory::new(cartridge(0xFF, 0x05, 0x03));
+    memory.store(0x2000, 0x3F);
+    assert_eq!(memory.load(0x4000), 0x3F);
+
+    memory.store(0x4000, 0x02);
+    memory.store(0xA000, 0x42);
+    assert_eq!(memory.load(0xA000), 0x42);
+
+    // Infrared mode, where nothing is ever seen
+    memory.store(0x0000, 0x0E);
+    assert_eq!(memory.load(0xA000), 0xC0);
+    memory.store(0x0000, 0x00);
+    assert_eq!(memory.load(0xA000), 0x42);
+}
+
+#[test]
+fn huc3() {
+    let mut memory = Memory::new(cartridge(0xFE, 0x05, 0x03));
+    memory.store(0x2000, 0x7F);
+    assert_eq!(memory.load(0x4000), 0x3F); // Only 64 banks there
+
+    memory.store(0x0000, 0x0A);
+    memory.store(0xA000, 0x42);
+    assert_eq!(memory.load(0xA000), 0x42);
+
+    // Two minutes later, copy the time to the clock memory and read the minutes back
+    memory.mapper.step(4_194_304 * 120);
+    memory.store(0x0000, 0x0B);
+    for &command in &[0x60, 0x40, 0x50, 0x10] {
+        memory.store(0xA000, command);
+    }
+    memory.store(0x0000, 0x0D);
+    assert_eq!(memory.load(0xA000) & 0x01, 0x01);
+    memory.store(0x0000, 0x0C);
+    assert_eq!(memory.load(0xA000), 0x92);
+}
+
+#[test]
+fn mmm01() {
+    // 256 kB with the menu in the last 32 kB
+    let mut cartridge = cartridge(0x00, 0x03, 0x00);
+    let menu = cartridge.rom.len() - 0x8000;
+    cartridge.rom[menu + 0x104..menu + 0x134].copy_from_slice(&LOGO);
+    cartridge.rom[menu + 0x147] = 0x0B;
+    cartridge.rom[menu + 0x14D] = Header::checksum(&cartridge.rom[menu..]);
+    assert_eq!(Kind::detect(&cartridge), Some(Kind::Mmm01));
+
+    let mut memory = Memory::new(cartridge);
+    assert_eq!(memory.load(0x0000), 0x0E);
+    assert_eq!(memory.load(0x4000), 0x0F);
+
+    // Pick the game starting at bank 8 with 8 banks, then start it
+    memory.store(0x2000, 0x08);
+    memory.store(0x6000, 0x30);
+    memory.store(0x0000, 0x40);
+    assert_eq!(memory.load(0x0000), 0x08);
+    assert_eq!(memory.load(0x4000), 0x09);
+
+    // The game can't get out of its banks
+    memory.store(0x2000, 0x03);
+    assert_eq!(memory.load(0x4000), 0x0B);
+    memory.store(0x2000, 0x1F);
+    assert_eq!(memory.load(0x4000), 0x0F);
+    assert_eq!(memory.load(0x0000), 0x08);
+}
+
+#[test]
+fn mmm01_needs_menu_header() {
+    // Just the type byte where the menu would be is only game data
+    let mut cartridge = cartridge(0x01, 0x03, 0x00);
+    let menu = cartridge.rom.len() - 0x8000;
+    cartridge.rom[menu + 0x147] = 0x0B;
+    assert_eq!(Kind::detect(&cartridge), Some(Kind::Mbc1));
+
+    // And so is a menu header with a bad checksum
+    cartridge.rom[menu + 0x104..menu + 0x134].copy_from_slice(&LOGO);
+    cartridge.rom[menu + 0x14D] = Header::checksum(&cartridge.rom[menu..]).wrapping_add(1);
+    assert_eq!(Kind::detect(&cartridge), Some(Kind::Mbc1));
+}
+
+#[test]
+fn mmm01_tiny_rom() {
+    // Not even the two banks of a menu, both halves see what there is
+    let mut rom = vec![0; 0x150];
+    rom[0x000] = 0x42;
+    rom[0x147] = 0x0B;
+    let mut memory = Memory::with_mapper(Kind::Mmm01, Cartridge::unchecked(rom).unwrap());
+    assert_eq!(memory.load(0x0000), 0x42);
+    assert_eq!(memory.load(0x4000), 0x42);
+}
+
+#[test]
+fn tiny_rom() {
+    // Just a header, forced onto every mapper
+    for name in Kind::names() {
+        let kind = Kind::from_name(name).unwrap();
+        let mut memory = Memory::with_mapper(kind, Cartridge::unchecked(vec![0; 0x150]).unwrap());
+        for address in (0x0000..0x8000).step_by(0x80) {
+            memory.store(address, 0xFF);
+            memory.store(address + 1, 0x0A);
+        }
+        for address in 0x0000..0xC000 {
+            memory.load(address);
+        }
+        for address in 0xA000..0xC000 {
+            memory.store(address, 0x55);
+        }
+        memory.mapper.step(4_194_304);
+        assert_eq!(memory.load(0x014F), 0x00, "{}", name);
+        assert_eq!(memory.load(0x0150), 0xFF, "{}", name);
+    }
+}
+
+fn mbc7() -> Memory {
+    let mut memory = Memory::new(cartridge(0x22, 0x03, 0x00));
+    memory.store(0x0000, 0x0A);
+    memory.store(0x4000, 0x40);
+    memory
+}
+
+#[test]
+fn mbc7_accelerometer() {
+    let mut memory = mbc7();
+    memory.mapper.tilt(0.5, -1.0);
+
+    // Nothing until latched
+    memory.store(0xA000, 0x55);
+    assert_eq!(memory.load(0xA020), 0x00);
+    assert_eq!(memory.load(0xA030), 0x80);
+
+    memory.store(0xA010, 0xAA);
+    let x = memory.load(0xA020) as u16 | (memory.load(0xA030) as u16) << 8;
+    let y = memory.load(0xA040) as u16 | (memory.load(0xA050) as u16) << 8;
+    assert_eq!((x, y), (0x81D0 + 56, 0x81D0 - 112));
+
+    // Both enables are needed
+    memory.store(0x4000, 0x00);
+    assert_eq!(memory.load(0xA020), 0xFF);
+}
+
+// Clocks bits into the EEPROM, returns what came out on each rising edge
+fn eeprom(memory: &mut Memory, bits: &str) -> String {
+    let mut output = String::new();
+    for bit in bits.chars() {
+        let data = if bit == '1' { 0x02 } else { 0x00 };
+        memory.store(0xA080, 0x80 | data);
+        memory.store(0xA080, 0xC0 | data);
+        output.push(if memory.load(0xA080) & 0x01 != 0 { '1' } else { '0' });
+    }
+    memory.store(0xA080, 0x00);
+    output
+}
+
+#[test]
+fn mbc7_eeprom() {
+    let mut memory = mbc7();
+    let write = "101000000100011110000111100"; // Start bit, WRITE $3C3C to word 2
+    let read = format!("11000000010{}", "0".repeat(16)); // Start bit, READ word 2
+
+    // Writes are ignored until enabled
+    eeprom(&mut memory, write);
+    assert_eq!(&eeprom(&mut memory, &read)[10..], "01111111111111111");
+
+    eeprom(&mut memory, "10011000000"); // EWEN
+    eeprom(&mut memory, write);
+    assert_eq!(&eeprom(&mut memory, &read)[10..], "00011110000111100");
+}
+
+#[test]
+fn names() {
+    for &name in &Kind::names() {
+        assert_eq!(Kind::from_name(name).unwrap().name(), name);
+    }
+    assert_eq!(Kind::from_name("mbc4"), None);
+}
+
+// A ROM with a bogus header, every 16 kB bank starting with its number
+fn unlicensed_rom(size: usize) -> Vec<u8> {
+    let mut rom = vec![0; size];
+    for bank in 0..size / 0x4000 {
+        rom[bank * 0x4000] = bank as u8;
+    }
+    rom[0x148] = 0xFF;
+    rom
+}
+
+#[test]
+fn wisdom_tree() {
+    let mut rom = unlicensed_rom(0x20000);
+    rom[0x134..0x134 + 11].copy_from_slice(b"WISDOM TREE");
+    assert!(Cartridge::new(rom.clone()).is_err());
+    assert_eq!(Kind::detect_unlicensed(&rom), Some(Kind::WisdomTree));
+
+    let cartridge = Cartridge::unchecked(rom).unwrap();
+    let mut memory = Memory::with_mapper(Kind::WisdomTree, cartridge);
+
+    // The address picks the 32 kB bank
+    memory.store(0x0002, 0xFF);
+    assert_eq!(memory.load(0x0000), 0x04);
+    assert_eq!(memory.load(0x4000), 0x05);
+}
+
+#[test]
+fn sachen() {
+    // The Nintendo logo only shows up through the scrambled addresses
+    let mut rom = unlicensed_rom(0x40000);
+    for (i, &byte) in LOGO.iter().enumerate() {
+        let address = 0x104 + i;
+        let scrambled = address & !0x53 | (address >> 6) & 1 | (address >> 4 & 1) << 1 |
+            (address >> 1 & 1) << 4 | (address & 1) << 6;
+        rom[scrambled] = byte;
+    }
+    assert_eq!(Kind::detect_unlicensed(&rom), Some(Kind::Sachen));
+
+    let mut memory = Memory::with_mapper(Kind::Sachen, Cartridge::unchecked(rom).unwrap());
+    memory.store(0x2000, 0x05);
+    assert_eq!(memory.load(0x4000), 0x05);
+
+    // The base bank and mask are locked
+    memory.store(0x0000, 0x08);
+    memory.store(0x4000, 0x08);
+    assert_eq!(memory.load(0x0000), 0x00);
+
+    // Until the ROM bank has bits 4 and 5 set
+    memory.store(0x2000, 0x30);
+    memory.store(0x0000, 0x08);
+    memory.store(0x4000, 0x08);
+    assert_eq!(memory.load(0x0000), 0x08);
+    memory.store(0x2000, 0x03);
+    assert_eq!(memory.load(0x4000), 0x0B);
+}
+
+#[test]
+fn m161() {
+    let mut rom = unlicensed_rom(0x40000);
+    rom[0x134..0x134 + 10].copy_from_slice(b"TETRIS SET");
+    rom[0x147] = 0x10;
+    assert_eq!(Kind::detect_unlicensed(&rom), Some(Kind::M161));
+
+    let mut memory = Memory::with_mapper(Kind::M161, Cartridge::unchecked(rom).unwrap());
+    memory.store(0x4000, 0x03);
+    assert_eq!(memory.load(0x0000), 0x06);
+    assert_eq!(memory.load(0x4000), 0x07);
+
+    // Only once
+    memory.store(0x4000, 0x01);
+    assert_eq!(memory.load(0x0000), 0x06);
+}
+
+#[test]
+fn licensed_carts_are_not_unlicensed() {
+    let mut cartridge = cartridge(0x01, 0x03, 0x00);
+    cartridge.rom[0x104..0x134].copy_from_slice(&LOGO);
+    assert_eq!(Kind::detect_unlicensed(&cartridge.rom), None);
+    assert_eq!(Kind::detect(&cartridge), Some(Kind::Mbc1));
+}
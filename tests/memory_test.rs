@@ -0,0 +1,70 @@
+extern crate yob;
+
+mod common;
+
+use yob::bus::Bus;
+
+#[test]
+fn oam_dma() {
+    let mut memory = common::memory();
+    for i in 0..0xA0 {
+        memory.store(0xC100 + i, i as u8 ^ 0x5A);
+    }
+
+    memory.store(0xFF46, 0xC1);
+    assert_eq!(memory.load(0xFF46), 0xC1);
+
+    // One cycle of setup before the transfer takes the bus
+    assert_eq!(memory.load(0xFE00), 0x00);
+    memory.tick(4);
+    assert_eq!(memory.load(0xFE00), 0xFF);
+    assert_eq!(memory.load(0xC100), 0xFF);
+
+    // HRAM is still there for the routine waiting on the transfer
+    memory.store(0xFF80, 0x42);
+    assert_eq!(memory.load(0xFF80), 0x42);
+
+    memory.tick(159 * 4);
+    assert_eq!(memory.load(0xFE00), 0xFF);
+    memory.tick(4);
+    for i in 0..0xA0 {
+        assert_eq!(memory.load(0xFE00 + i), i as u8 ^ 0x5A);
+    }
+}
+
+#[test]
+fn oam_dma_progress() {
+    let mut memory = common::memory();
+    for i in 0..0xA0 {
+        memory.store(0xC000 + i, 0xAA);
+    }
+
+    memory.store(0xFF46, 0xC0);
+    memory.tick(11 * 4);
+    assert_eq!(memory.gpu.oam[9], 0xAA);
+    assert_eq!(memory.gpu.oam[10], 0x00);
+}
+
+#[test]
+fn oam_dma_echo_source() {
+    let mut memory = common::memory();
+    memory.store(0xDE00, 0x12);
+    memory.store(0xFF46, 0xFE);
+    memory.tick(161 * 4);
+    assert_eq!(memory.load(0xFE00), 0x12);
+}
+
+#[test]
+fn serial_transfer() {
+    let mut memory = common::memory();
+    memory.store(0xFF01, 0x42);
+    memory.store(0xFF02, 0x81);
+    assert_eq!(memory.serial.output, vec![0x42]);
+    assert_eq!(memory.load(0xFF02), 0xFF);
+
+    // Nobody on the other end, 0xFF comes back in
+    memory.tick(4096);
+    assert_eq!(memory.load(0xFF01), 0xFF);
+    assert_eq!(memory.load(0xFF02), 0x7F);
+    assert_eq!(memory.interrupt_flags & 0x08, 0x08);
+}
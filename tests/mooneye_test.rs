@@ -0,0 +1,111 @@
+extern crate yob;
+
+mod common;
+
+use std::fs;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use yob::cartridge::Cartridge;
+use yob::cpu::{Cpu, IllegalOpcodePolicy};
+use yob::memory::Memory;
+
+// Mooneye's test ROMs end on LD B,B with the Fibonacci numbers in the registers
+// when they pass, or 0x42 everywhere when they fail. Every ROM found under
+// roms/mooneye is run, nothing happens if there are none.
+// https://github.com/Gekkio/mooneye-test-suite
+
+// Emulated time after which a ROM is considered stuck
+const TIMEOUT_CYCLES: u64 = 4_194_304 * 20;
+
+fn run(cartridge: Cartridge) -> Result<(), String> {
+    let mut cpu = Cpu::new(Memory::new(cartridge));
+    cpu.reset();
+    cpu.ld_b_b_breakpoint = true;
+    cpu.illegal_opcode_policy = IllegalOpcodePolicy::Error;
+
+    while !cpu.breakpoint {
+        if cpu.cycles >= TIMEOUT_CYCLES {
+            return Err("timed out".to_string());
+        }
+        if let Err(error) = cpu.step() {
+            return Err(error.to_string());
+        }
+    }
+
+    let r = &cpu.registers;
+    match (r.b, r.c, r.d, r.e, r.h, r.l) {
+        (3, 5, 8, 13, 21, 34) => Ok(()),
+        (0x42, 0x42, 0x42, 0x42, 0x42, 0x42) => Err("failed".to_string()),
+        _ => Err(format!("unexpected registers {:?}", cpu)),
+    }
+}
+
+fn find_roms(directory: &Path, roms: &mut Vec<PathBuf>) {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        if path.is_dir() {
+            find_roms(&path, roms);
+        } else if path.extension().map_or(false, |extension| extension == "gb") {
+            roms.push(path);
+        }
+    }
+}
+
+#[test]
+fn mooneye() {
+    let directory = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("roms/mooneye");
+    let mut roms = Vec::new();
+    find_roms(&directory, &mut roms);
+    roms.sort();
+
+    let mut failures = Vec::new();
+    for path in &roms {
+        let name = path.strip_prefix(&directory).unwrap_or(path).display().to_string();
+        let mut file = File::open(path).unwrap();
+
+        let result = Cartridge::load(&mut file)
+            .map_err(|error| error.to_string())
+            .and_then(run);
+
+        match result {
+            Ok(()) => println!("PASS {}", name),
+            Err(reason) => {
+                println!("FAIL {} ({})", name, reason);
+                failures.push(name);
+            }
+        }
+    }
+
+    println!("{}/{} Mooneye tests passed", roms.len() - failures.len(), roms.len());
+    assert!(failures.is_empty(), "Failing Mooneye tests:\n{}", failures.join("\n"));
+}
+
+// Loads the registers and hits the breakpoint like the test ROMs do
+fn breakpoint_rom(values: [u8; 6]) -> Cartridge {
+    let mut rom = common::rom(0x00, 0x00);
+    let main: &[u8] = &[
+        0x06, values[0], // LD B,n
+        0x0E, values[1], // LD C,n
+        0x16, values[2], // LD D,n
+        0x1E, values[3], // LD E,n
+        0x26, values[4], // LD H,n
+        0x2E, values[5], // LD L,n
+        0x40,            // LD B,B
+        0x18, 0xFE,      // JR -2
+    ];
+    rom[0x100..0x100 + main.len()].copy_from_slice(main);
+    common::cartridge(rom)
+}
+
+#[test]
+fn ld_b_b_breakpoint() {
+    assert_eq!(run(breakpoint_rom([3, 5, 8, 13, 21, 34])), Ok(()));
+    assert_eq!(run(breakpoint_rom([0x42; 6])), Err("failed".to_string()));
+    assert!(run(breakpoint_rom([0; 6])).is_err());
+}
@@ -0,0 +1,174 @@
+extern crate yob;
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use yob::bus::Bus;
+use yob::cartridge::Header;
+use yob::memory::Memory;
+use yob::save::SaveFile;
+
+fn memory(cartridge_type: u8, ram_size_code: u8) -> Memory {
+    let mut rom = common::rom(cartridge_type, 0x00);
+    rom[0x149] = ram_size_code;
+    let mut memory = Memory::new(common::cartridge(rom));
+    memory.store(0x0000, 0x0A);
+    memory
+}
+
+// A fresh path for each test, as they run in parallel
+fn path(name: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("yob-{}-{}.sav", name, std::process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+#[test]
+fn battery() {
+    assert_eq!(memory(0x03, 0x02).mapper.ram().len(), 0x2000);
+    let header = |cartridge_type| {
+        let mut rom = vec![0; 0x150];
+        rom[0x147] = cartridge_type;
+        Header::read(&rom).unwrap()
+    };
+    assert!(header(0x03).has_battery());
+    assert!(header(0x10).has_battery());
+    assert!(!header(0x01).has_battery());
+    assert!(!header(0x12).has_battery());
+}
+
+#[test]
+fn save_and_load() {
+    let path = path("save_and_load");
+    let mut memory = memory(0x03, 0x02);
+    memory.store(0xA000, 0x42);
+    memory.store(0xBFFF, 0x24);
+
+    let mut save = SaveFile::new(&path);
+    save.write(&mut *memory.mapper).unwrap();
+    assert_eq!(fs::metadata(&path).unwrap().len(), 0x2000);
+
+    let mut memory = self::memory(0x03, 0x02);
+    SaveFile::new(&path).load(&mut *memory.mapper).unwrap();
+    assert_eq!(memory.load(0xA000), 0x42);
+    assert_eq!(memory.load(0xBFFF), 0x24);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn missing_file() {
+    let path = path("missing_file");
+    let mut memory = memory(0x03, 0x02);
+    assert!(SaveFile::new(&path).load(&mut *memory.mapper).is_ok());
+    assert_eq!(memory.load(0xA000), 0x00);
+}
+
+#[test]
+fn autosave() {
+    let path = path("autosave");
+    let mut memory = memory(0x03, 0x02);
+    let mut save = SaveFile::new(&path);
+    save.load(&mut *memory.mapper).unwrap();
+
+    // Nothing changed
+    save.autosave(&mut *memory.mapper, 4_194_304 * 10).unwrap();
+    assert!(!path.exists());
+
+    memory.store(0xA000, 0x42);
+    save.autosave(&mut *memory.mapper, 4_194_304).unwrap();
+    assert!(!path.exists());
+    save.autosave(&mut *memory.mapper, 4_194_304 * 4).unwrap();
+    assert_eq!(fs::read(&path).unwrap()[0], 0x42);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn rtc_footer() {
+    let path = path("rtc_footer");
+    let mut memory = memory(0x10, 0x02);
+    memory.store(0xA000, 0x42);
+    for &(register, value) in &[(0x08, 1), (0x09, 2), (0x0A, 3), (0x0B, 4), (0x0C, 0x81)] {
+        memory.store(0x4000, register);
+        memory.store(0xA000, value);
+    }
+
+    SaveFile::new(&path).write(&mut *memory.mapper).unwrap();
+    let contents = fs::read(&path).unwrap();
+    assert_eq!(contents.len(), 0x2000 + 48);
+    assert_eq!(contents[0], 0x42);
+    assert_eq!(&contents[0x2000..0x2000 + 20], &[1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 4, 0, 0, 0, 0x81, 0, 0, 0]);
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let mut timestamp = [0; 8];
+    timestamp.copy_from_slice(&contents[0x2000 + 40..]);
+    assert!(now - u64::from_le_bytes(timestamp) < 5);
+
+    // Saved an hour ago
+    let mut contents = contents;
+    let then = (now - 3600).to_le_bytes();
+    contents[0x2000 + 40..].copy_from_slice(&then);
+    fs::write(&path, &contents).unwrap();
+
+    let mut memory = self::memory(0x10, 0x02);
+    SaveFile::new(&path).load(&mut *memory.mapper).unwrap();
+    let rtc = memory.mapper.rtc().unwrap();
+    assert_eq!((rtc.days, rtc.hours, rtc.minutes, rtc.seconds), (260, 4, 2, 1));
+    assert!(rtc.carry);
+
+    // Or not, with a frozen clock and an old 44 bytes footer
+    contents.truncate(0x2000 + 44);
+    fs::write(&path, &contents).unwrap();
+    let mut memory = self::memory(0x10, 0x02);
+    let mut save = SaveFile::new(&path);
+    save.wall_clock = false;
+    save.load(&mut *memory.mapper).unwrap();
+    assert_eq!(memory.mapper.rtc().unwrap().hours, 3);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn huc3_clock_footer() {
+    let path = path("huc3_clock_footer");
+    let mut memory = memory(0xFE, 0x02);
+    memory.store(0xA000, 0x42);
+    memory.mapper.huc3_clock().unwrap().set(100, 7);
+
+    SaveFile::new(&path).write(&mut *memory.mapper).unwrap();
+    let contents = fs::read(&path).unwrap();
+    assert_eq!(contents.len(), 0x2000 + 17);
+    assert_eq!(contents[0], 0x42);
+    assert_eq!(&contents[0x2000 + 8..], &[100, 0, 7, 0, 0, 0, 0, 0, 0]);
+
+    // Saved a day and an hour ago, a minute before midnight
+    let mut contents = contents;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    contents[0x2000..0x2000 + 8].copy_from_slice(&(now - 90000).to_le_bytes());
+    contents[0x2000 + 8..0x2000 + 10].copy_from_slice(&1439u16.to_le_bytes());
+    fs::write(&path, &contents).unwrap();
+
+    let mut memory = self::memory(0xFE, 0x02);
+    SaveFile::new(&path).load(&mut *memory.mapper).unwrap();
+    let clock = memory.mapper.huc3_clock().unwrap();
+    assert_eq!((clock.days, clock.minutes), (9, 59));
+
+    // Out of range minutes wrap to the next day instead of counting up forever
+    contents[0x2000 + 8..0x2000 + 10].copy_from_slice(&0xFFFFu16.to_le_bytes());
+    fs::write(&path, &contents).unwrap();
+    let mut memory = self::memory(0xFE, 0x02);
+    let mut save = SaveFile::new(&path);
+    save.wall_clock = false;
+    save.load(&mut *memory.mapper).unwrap();
+    assert_eq!(memory.mapper.huc3_clock().unwrap().minutes, 0xFFF);
+    memory.mapper.step(4_194_304 * 60);
+    let clock = memory.mapper.huc3_clock().unwrap();
+    assert_eq!((clock.days, clock.minutes), (8, 0));
+
+    fs::remove_file(&path).unwrap();
+}
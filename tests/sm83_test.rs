@@ -0,0 +1,250 @@
+extern crate serde_json;
+extern crate yob;
+
+use std::fs;
+use std::fs::File;
+use std::path::PathBuf;
+
+use serde_json::Value;
+
+use yob::bus::Bus;
+use yob::cpu::Cpu;
+
+// Runs the sm83 vectors from https://github.com/SingleStepTests/sm83, one JSON
+// file per opcode, found in tests/sm83/v1. Nothing happens if they aren't there.
+
+// What happened on the bus during one machine cycle, None for internal cycles
+type BusCycle = Option<(u16, u8, Access)>;
+
+#[derive(Copy,Clone,PartialEq,Debug)]
+enum Access { Read, Write }
+
+// 64 kB of RAM remembering every access of the CPU, one entry per machine cycle.
+// The CPU peeks at the interrupt registers, anything else goes on the record.
+struct RecordingBus {
+    memory: Vec<u8>,
+    cycles: Vec<BusCycle>,
+    accesses: usize,
+}
+
+impl RecordingBus {
+    fn new() -> RecordingBus {
+        RecordingBus { memory: vec![0; 0x10000], cycles: Vec::new(), accesses: 0 }
+    }
+
+    // An access before the first tick or a second one in the same machine
+    // cycle has no slot, the count catches it
+    fn record(&mut self, address: u16, value: u8, access: Access) {
+        self.accesses += 1;
+        if let Some(cycle) = self.cycles.last_mut() {
+            if cycle.is_none() {
+                *cycle = Some((address, value, access));
+            }
+        }
+    }
+}
+
+impl Bus for RecordingBus {
+    fn load(&mut self, address: u16) -> u8 {
+        let value = self.memory[address as usize];
+        self.record(address, value, Access::Read);
+        value
+    }
+
+    fn store(&mut self, address: u16, value: u8) {
+        self.memory[address as usize] = value;
+        self.record(address, value, Access::Write);
+    }
+
+    fn peek(&mut self, address: u16) -> u8 {
+        self.memory[address as usize]
+    }
+
+    fn tick(&mut self, cycles: u64) {
+        for _ in 0..cycles / 4 {
+            self.cycles.push(None);
+        }
+    }
+}
+
+fn number(state: &Value, key: &str) -> u16 {
+    state[key].as_u64().unwrap_or(0) as u16
+}
+
+fn setup(state: &Value) -> Cpu<RecordingBus> {
+    let mut cpu = Cpu::new(RecordingBus::new());
+    {
+        let r = &mut cpu.registers;
+        r.a = number(state, "a") as u8;
+        r.f = number(state, "f") as u8;
+        r.b = number(state, "b") as u8;
+        r.c = number(state, "c") as u8;
+        r.d = number(state, "d") as u8;
+        r.e = number(state, "e") as u8;
+        r.h = number(state, "h") as u8;
+        r.l = number(state, "l") as u8;
+        r.pc = number(state, "pc");
+        r.sp = number(state, "sp");
+    }
+    cpu.ime = number(state, "ime") != 0;
+
+    if let Some(ram) = state["ram"].as_array() {
+        for entry in ram {
+            cpu.bus.memory[entry[0].as_u64().unwrap() as usize] = entry[1].as_u64().unwrap() as u8;
+        }
+    }
+    if state.get("ie").is_some() {
+        cpu.bus.memory[0xFFFF] = number(state, "ie") as u8;
+    }
+
+    cpu
+}
+
+fn expected_cycle(cycle: &Value) -> BusCycle {
+    let kind = cycle[2].as_str().unwrap_or("---");
+    let access = if kind.starts_with('r') {
+        Access::Read
+    } else if kind.contains('w') {
+        Access::Write
+    } else {
+        return None;
+    };
+
+    Some((cycle[0].as_u64().unwrap_or(0) as u16, cycle[1].as_u64().unwrap_or(0) as u8, access))
+}
+
+// Runs one vector, returns what went wrong
+fn run(test: &Value) -> Result<(), String> {
+    let mut cpu = setup(&test["initial"]);
+    cpu.step().map_err(|error| error.to_string())?;
+
+    let expected = &test["final"];
+    let r = &cpu.registers;
+    let registers = [
+        ("A", r.a as u16, "a"), ("F", r.f as u16, "f"), ("B", r.b as u16, "b"),
+        ("C", r.c as u16, "c"), ("D", r.d as u16, "d"), ("E", r.e as u16, "e"),
+        ("H", r.h as u16, "h"), ("L", r.l as u16, "l"), ("PC", r.pc, "pc"), ("SP", r.sp, "sp"),
+    ];
+    for &(name, value, key) in &registers {
+        if value != number(expected, key) {
+            return Err(format!("{} is {:04X}, expected {:04X}", name, value, number(expected, key)));
+        }
+    }
+
+    // EI only takes effect after the next instruction, count it as enabled
+    let ime = cpu.ime || cpu.ei_delay > 0;
+    if expected.get("ime").is_some() && ime != (number(expected, "ime") != 0) {
+        return Err(format!("IME is {}, expected {}", ime, number(expected, "ime")));
+    }
+
+    if let Some(ram) = expected["ram"].as_array() {
+        for entry in ram {
+            let address = entry[0].as_u64().unwrap() as usize;
+            let value = entry[1].as_u64().unwrap() as u8;
+            if cpu.bus.memory[address] != value {
+                return Err(format!("({:04X}) is {:02X}, expected {:02X}",
+                                   address, cpu.bus.memory[address], value));
+            }
+        }
+    }
+
+    let cycles: Vec<BusCycle> = test["cycles"].as_array()
+        .map(|cycles| cycles.iter().map(expected_cycle).collect())
+        .unwrap_or_default();
+    let accesses = cycles.iter().filter(|cycle| cycle.is_some()).count();
+    if cpu.bus.accesses != accesses {
+        return Err(format!("{} bus accesses, expected {}", cpu.bus.accesses, accesses));
+    }
+    if cpu.bus.cycles != cycles {
+        return Err(format!("bus cycles are {:?}, expected {:?}", cpu.bus.cycles, cycles));
+    }
+
+    Ok(())
+}
+
+// Runs every vector of a file, returns how many failed and the first failure
+fn run_all(tests: &Value) -> (usize, usize, Option<String>) {
+    let tests = tests.as_array().map(|tests| tests.as_slice()).unwrap_or(&[]);
+    let mut failed = 0;
+    let mut first = None;
+
+    for test in tests {
+        if let Err(error) = run(test) {
+            failed += 1;
+            if first.is_none() {
+                first = Some(format!("{}: {}", test["name"].as_str().unwrap_or("?"), error));
+            }
+        }
+    }
+
+    (tests.len(), failed, first)
+}
+
+#[test]
+fn sm83() {
+    let directory = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/sm83/v1");
+    let mut paths: Vec<PathBuf> = match fs::read_dir(&directory) {
+        Ok(entries) => entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).collect(),
+        Err(_) => return,
+    };
+    paths.retain(|path| path.extension().map_or(false, |extension| extension == "json"));
+    paths.sort();
+
+    let mut failures = Vec::new();
+    for path in &paths {
+        let opcode = path.file_stem().unwrap().to_string_lossy().into_owned();
+        let tests: Value = serde_json::from_reader(File::open(path).unwrap()).unwrap();
+
+        let (total, failed, first) = run_all(&tests);
+        if failed > 0 {
+            println!("FAIL {}: {}/{} failed, first was {}", opcode, failed, total, first.unwrap());
+            failures.push(opcode);
+        }
+    }
+
+    println!("{}/{} opcodes passed", paths.len() - failures.len(), paths.len());
+    assert!(failures.is_empty(), "Failing opcodes: {}", failures.join(", "));
+}
+
+// A few vectors in the same format, to keep the harness itself honest
+#[test]
+fn harness() {
+    let tests: Value = serde_json::from_str(r#"[
+        {
+            "name": "c3 0000",
+            "initial": { "pc": 256, "sp": 65534, "a": 1, "b": 0, "c": 0, "d": 0, "e": 0, "f": 0,
+                         "h": 0, "l": 0, "ime": 0, "ie": 0, "ram": [[256, 195], [257, 80], [258, 1]] },
+            "final": { "pc": 336, "sp": 65534, "a": 1, "b": 0, "c": 0, "d": 0, "e": 0, "f": 0,
+                       "h": 0, "l": 0, "ime": 0, "ram": [[256, 195], [257, 80], [258, 1]] },
+            "cycles": [[256, 195, "r-m"], [257, 80, "r-m"], [258, 1, "r-m"], null]
+        },
+        {
+            "name": "c5 0000",
+            "initial": { "pc": 4096, "sp": 53248, "a": 0, "b": 18, "c": 52, "d": 0, "e": 0, "f": 0,
+                         "h": 0, "l": 0, "ime": 1, "ie": 0, "ram": [[4096, 197]] },
+            "final": { "pc": 4097, "sp": 53246, "a": 0, "b": 18, "c": 52, "d": 0, "e": 0, "f": 0,
+                       "h": 0, "l": 0, "ime": 1, "ram": [[4096, 197], [53247, 18], [53246, 52]] },
+            "cycles": [[4096, 197, "r-m"], [53247, 0, "---"], [53247, 18, "-wm"], [53246, 52, "-wm"]]
+        },
+        {
+            "name": "34 0000",
+            "initial": { "pc": 0, "sp": 0, "a": 0, "b": 0, "c": 0, "d": 0, "e": 0, "f": 16,
+                         "h": 192, "l": 0, "ime": 0, "ie": 0, "ram": [[0, 52], [49152, 255]] },
+            "final": { "pc": 1, "sp": 0, "a": 0, "b": 0, "c": 0, "d": 0, "e": 0, "f": 176,
+                       "h": 192, "l": 0, "ime": 0, "ram": [[0, 52], [49152, 0]] },
+            "cycles": [[0, 52, "r-m"], [49152, 255, "r-m"], [49152, 0, "-wm"]]
+        }
+    ]"#).unwrap();
+
+    assert_eq!(run_all(&tests), (3, 0, None));
+
+    // A wrong expectation gets reported
+    let mut broken = tests.clone();
+    broken[2]["final"]["f"] = Value::from(0);
+    assert_eq!(run_all(&broken), (3, 1, Some("34 0000: F is 00B0, expected 0000".to_string())));
+
+    // So does an access the vector doesn't have
+    let mut broken = tests.clone();
+    broken[0]["cycles"][2] = Value::Null;
+    assert_eq!(run_all(&broken), (3, 1, Some("c3 0000: 3 bus accesses, expected 2".to_string())));
+}
@@ -0,0 +1,52 @@
+extern crate yob;
+
+use yob::timer::Timer;
+
+#[test]
+fn div() {
+    let mut timer = Timer::new();
+    timer.step(256);
+    assert_eq!(timer.load(0x04), 1);
+
+    timer.store(0x04, 0x42);
+    assert_eq!(timer.load(0x04), 0);
+}
+
+#[test]
+fn tima_frequencies() {
+    for &(tac, period) in &[(0x04, 1024), (0x05, 16), (0x06, 64), (0x07, 256)] {
+        let mut timer = Timer::new();
+        timer.store(0x07, tac);
+        timer.step(period * 3);
+        assert_eq!(timer.load(0x05), 3);
+    }
+}
+
+#[test]
+fn tima_disabled() {
+    let mut timer = Timer::new();
+    timer.store(0x07, 0x01);
+    timer.step(1024);
+    assert_eq!(timer.load(0x05), 0);
+}
+
+#[test]
+fn tima_overflow() {
+    let mut timer = Timer::new();
+    timer.store(0x06, 0xAB);
+    timer.store(0x05, 0xFF);
+    timer.store(0x07, 0x05);
+    timer.step(16);
+    assert_eq!(timer.load(0x05), 0xAB);
+    assert!(timer.interrupt);
+}
+
+#[test]
+fn div_reset_falling_edge() {
+    // Resetting DIV while the selected bit is high ticks TIMA
+    let mut timer = Timer::new();
+    timer.store(0x07, 0x05);
+    timer.step(8);
+    timer.store(0x04, 0);
+    assert_eq!(timer.load(0x05), 1);
+}
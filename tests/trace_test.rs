@@ -0,0 +1,75 @@
+extern crate yob;
+
+mod common;
+
+use std::cell::RefCell;
+use std::io;
+use std::io::Write;
+use std::rc::Rc;
+
+use yob::bus::{Bus, FlatBus};
+use yob::cpu::Cpu;
+use yob::trace::Tracer;
+
+// Keeps what the tracer wrote around after it's moved into the CPU
+#[derive(Clone)]
+struct Output(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> { Ok(()) }
+}
+
+#[test]
+fn gameboy_doctor_format() {
+    let output = Output(Rc::new(RefCell::new(Vec::new())));
+    let mut cpu = Cpu::new(FlatBus::new());
+    cpu.reset();
+    cpu.tracer = Some(Tracer::new(output.clone()));
+
+    cpu.bus.store(0x100, 0x00); // NOP
+    cpu.bus.store(0x101, 0xC3); // JP $0213
+    cpu.bus.store(0x102, 0x13);
+    cpu.bus.store(0x103, 0x02);
+    cpu.step().unwrap();
+    cpu.step().unwrap();
+
+    let log = String::from_utf8(output.0.borrow().clone()).unwrap();
+    assert_eq!(log, "A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0100 PCMEM:00,C3,13,02\n\
+                     A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0101 PCMEM:C3,13,02,00\n");
+}
+
+#[test]
+fn interrupts_are_not_traced() {
+    let output = Output(Rc::new(RefCell::new(Vec::new())));
+    let mut cpu = Cpu::new(FlatBus::new());
+    cpu.tracer = Some(Tracer::new(output.clone()));
+    cpu.ime = true;
+    cpu.bus.store(0xFFFF, 0x01);
+    cpu.bus.store(0xFF0F, 0x01);
+    cpu.step().unwrap();
+    assert!(output.0.borrow().is_empty());
+
+    cpu.step().unwrap();
+    assert!(String::from_utf8(output.0.borrow().clone()).unwrap().contains("PC:0040"));
+}
+
+#[test]
+fn tracing_has_no_side_effects() {
+    let mut memory = common::memory();
+    memory.strict = true;
+
+    // A NOP in the serial data register, with an unused register right after
+    let output = Output(Rc::new(RefCell::new(Vec::new())));
+    let mut cpu = Cpu::new(memory);
+    cpu.tracer = Some(Tracer::new(output.clone()));
+    cpu.registers.pc = 0xFF01;
+    cpu.bus.store(0xFF01, 0x00);
+    assert!(cpu.step().is_ok());
+
+    let log = String::from_utf8(output.0.borrow().clone()).unwrap();
+    assert!(log.ends_with("PC:FF01 PCMEM:00,7E,FF,00\n"), "{}", log);
+}